@@ -1,29 +1,75 @@
 //! Spawn the main level.
 
-use crate::racing::{ControlPoints, CurrentTrack, Curves, RaceTrack, TrackPart, TracksAsset, TracksAssetLoader};
+use crate::racing::arc_length::TRACK_SAMPLE_SPACING;
+use crate::racing::bridge::{
+    BRIDGE_RAMP_LENGTH, BRIDGE_Z, BridgeRamp, BridgeSpan, update_track_level,
+};
+use crate::racing::cup::{Championship, CupsAsset, CupsAssetLoader, DEFAULT_LAPS};
+use crate::racing::mesh::{TrackMeshSettings, build_bridge_mesh, build_track_mesh};
+use crate::racing::physics::{
+    Car, RoadSensor, TrackLayer, road_collider, span_collider, surface_collider, update_on_road,
+    wall_colliders,
+};
+use crate::racing::library::TrackLibrary;
+use crate::racing::progress::{RaceCourse, RaceProgress};
+use crate::racing::racing_line::{RacingLineCache, ShowRacingLine};
+use crate::screens::track_select::QuickRace;
+use crate::racing::surface::{SurfaceSensor, update_current_surface};
+use crate::racing::{CurrentTrack, RaceTrack, TrackPart, TracksAsset, TracksAssetLoader};
 use crate::{
     asset_tracking::LoadResource,
     audio::music,
-    demo::player::{PlayerAssets, player},
-    screens::Screen,
+    demo::player::{Player, PlayerAssets, player},
+    screens::{Screen, track_error::TrackLoadError},
 };
 use avian2d::PhysicsPlugins;
-use avian2d::prelude::{Collider, Gravity, PhysicsDebugPlugin, RigidBody};
-use bevy::asset::RenderAssetUsages;
-use bevy::color::palettes::basic::GRAY;
+use avian2d::prelude::{Gravity, PhysicsDebugPlugin, RigidBody, Sensor};
+use bevy::asset::LoadState;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::color::palettes::basic::{GRAY, SILVER};
 use bevy::prelude::*;
-use bevy::render::mesh::{Indices, PrimitiveTopology};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_plugins((PhysicsPlugins::default(), PhysicsDebugPlugin::default()))
         .insert_resource(Gravity::ZERO)
         .init_resource::<CurrentTrack>()
+        .init_resource::<CurrentRace>()
+        .init_resource::<RacingLineCache>()
+        .init_resource::<ShowRacingLine>()
         .init_asset::<TracksAsset>()
         .init_asset_loader::<TracksAssetLoader>()
+        .init_asset::<CupsAsset>()
+        .init_asset_loader::<CupsAssetLoader>()
         .register_type::<LevelAssets>()
-        .load_resource::<LevelAssets>();
+        .load_resource::<LevelAssets>()
+        .add_systems(
+            Update,
+            report_track_load_failure.run_if(in_state(Screen::Loading)),
+        )
+        .add_systems(
+            Update,
+            (
+                reload_current_track,
+                update_on_road,
+                update_current_surface,
+                update_track_level,
+                (update_race_course, update_race_progress, finish_race).chain(),
+                toggle_racing_line.run_if(input_just_pressed(KeyCode::KeyI)),
+                draw_racing_line,
+            )
+                .run_if(in_state(Screen::Gameplay)),
+        );
 }
 
+/// The tracks file raced on in gameplay, relative to the assets folder.
+pub const TRACKS_PATH: &str = "tracks/race.tracks";
+
+/// The cups raced in gameplay, relative to the assets folder.
+pub const CUPS_PATH: &str = "cups/championship.cups";
+
+/// The name the player goes by in the [`Championship`] standings.
+pub const PLAYER_ENTRANT: &str = "Player";
+
 #[derive(Resource, Asset, Clone, Reflect)]
 #[reflect(Resource)]
 pub struct LevelAssets {
@@ -31,6 +77,8 @@ pub struct LevelAssets {
     music: Handle<AudioSource>,
     #[dependency]
     track: Handle<TracksAsset>,
+    #[dependency]
+    pub cups: Handle<CupsAsset>,
 }
 
 impl FromWorld for LevelAssets {
@@ -38,22 +86,78 @@ impl FromWorld for LevelAssets {
         let assets = world.resource::<AssetServer>();
         Self {
             music: assets.load("audio/music/Fluffing A Duck.ogg"),
-            track: assets.load("race.tracks"),
+            track: assets.load(TRACKS_PATH),
+            cups: assets.load(CUPS_PATH),
         }
     }
 }
 
-/// A system that spawns the main level.
+/// The race under way in gameplay.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CurrentRace {
+    /// The laps raced if the track is closed.
+    pub laps: u32,
+    /// Whether the race is scored for the [`Championship`] under way, which quick races are not.
+    pub championship: bool,
+    /// The [`CurrentTrack`] measured for progress.
+    pub course: Option<RaceCourse>,
+}
+
+/// A system that spawns the main level. The track raced is the one picked for a [`QuickRace`], or
+/// else the next one in the [`Championship`] picked on the cup select screen.
 pub fn spawn_level(
     mut commands: Commands,
     level_assets: Res<LevelAssets>,
     player_assets: Res<PlayerAssets>,
     mut track_assets: ResMut<Assets<TracksAsset>>,
+    championship: Option<Res<Championship>>,
+    quick_race: Option<Res<QuickRace>>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     mut current_track: ResMut<CurrentTrack>,
+    mut current_race: ResMut<CurrentRace>,
+    mut next_screen: ResMut<NextState<Screen>>,
 ) {
-    let tracks = track_assets.get_mut(&level_assets.track).unwrap();
-    current_track.0 = tracks.get_next_track().cloned();
+    *current_race = CurrentRace {
+        laps: DEFAULT_LAPS,
+        championship: quick_race.is_none(),
+        course: None,
+    };
+    let track = match quick_race {
+        Some(quick_race) => {
+            // A quick race is raced once, outside the championship.
+            commands.remove_resource::<QuickRace>();
+            Ok(quick_race.0.clone())
+        }
+        None => next_championship_track(
+            track_assets.get_mut(&level_assets.track).unwrap(),
+            championship.as_deref(),
+            &mut current_race.laps,
+        ),
+    };
+    // Pole position, on the grid behind the start line of a lap race and of a sprint alike.
+    let start = track
+        .as_ref()
+        .ok()
+        .and_then(|track| track.starting_grid().first().map(|slot| slot.position))
+        .unwrap_or_default();
+    match track {
+        Ok(track) => current_track.0 = Some(track),
+        Err(message) => {
+            // A championship track missing from the library is a mistake in the cup.
+            let source = match championship {
+                Some(championship) if current_race.championship && !championship.is_finished() => {
+                    CUPS_PATH
+                }
+                _ => TRACKS_PATH,
+            };
+            commands.insert_resource(TrackLoadError {
+                source: source.to_string(),
+                message,
+            });
+            next_screen.set(Screen::TrackError);
+            return;
+        }
+    }
 
     commands.spawn((
         Name::new("Level"),
@@ -61,7 +165,7 @@ pub fn spawn_level(
         Visibility::default(),
         StateScoped(Screen::Gameplay),
         children![
-            player(400.0, &player_assets, &mut texture_atlas_layouts),
+            player(400.0, start, &player_assets, &mut texture_atlas_layouts),
             (
                 Name::new("Gameplay Music"),
                 music(level_assets.music.clone())
@@ -70,6 +174,241 @@ pub fn spawn_level(
     ));
 }
 
+/// The track of the next race in `championship`, looked up in the track library, with its laps
+/// put in `laps`. Without a championship under way the file's next track is raced.
+fn next_championship_track(
+    tracks: &mut TracksAsset,
+    championship: Option<&Championship>,
+    laps: &mut u32,
+) -> Result<RaceTrack, String> {
+    let Some(race) = championship.and_then(Championship::current_race) else {
+        return tracks
+            .get_next_track()
+            .cloned()
+            .ok_or_else(|| "The file does not contain any tracks".to_string());
+    };
+    *laps = race.laps;
+    let library = TrackLibrary::scan();
+    for err in &library.errors {
+        warn!("{err}");
+    }
+    library
+        .find(&race.track_name)
+        .map(|entry| entry.track.clone())
+        .ok_or_else(|| {
+            format!(
+                "No file in the track library contains the track \"{}\"",
+                race.track_name
+            )
+        })
+}
+
+/// Sends the player to the error screen if the tracks or cups failed to load while they were
+/// waiting.
+fn report_track_load_failure(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let failure = load_failure::<TracksAsset>(&asset_server, TRACKS_PATH)
+        .or_else(|| load_failure::<CupsAsset>(&asset_server, CUPS_PATH));
+    if let Some(error) = failure {
+        commands.insert_resource(error);
+        next_screen.set(Screen::TrackError);
+    }
+}
+
+/// The error the asset at `path` failed to load with, if it has.
+fn load_failure<A: Asset>(asset_server: &AssetServer, path: &str) -> Option<TrackLoadError> {
+    let handle = asset_server.get_handle::<A>(path)?;
+    match asset_server.load_state(&handle) {
+        LoadState::Failed(error) => Some(TrackLoadError {
+            source: path.to_string(),
+            message: error.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Picks up changes to the tracks file while racing. The current track is swapped for its new
+/// version, which makes [`instantiate_track`] rebuild the road, and every car is moved to the same
+/// place relative to the new track.
+fn reload_current_track(
+    mut events: EventReader<AssetEvent<TracksAsset>>,
+    level_assets: Res<LevelAssets>,
+    track_assets: Res<Assets<TracksAsset>>,
+    mut current_track: ResMut<CurrentTrack>,
+    mut cars: Query<(&mut Transform, Option<&mut RaceProgress>), With<Car>>,
+) {
+    let modified = events.read().any(|event| {
+        matches!(event, AssetEvent::Modified { id } if *id == level_assets.track.id())
+    });
+    if !modified {
+        return;
+    }
+    let (Some(tracks), Some(old)) = (
+        track_assets.get(&level_assets.track),
+        current_track.0.as_ref(),
+    ) else {
+        return;
+    };
+
+    // Follow the track by name, falling back to whichever track the file has selected.
+    let Some(new) = tracks
+        .tracks
+        .iter()
+        .find(|track| track.track_name == old.track_name)
+        .or_else(|| tracks.get_current_track())
+        .or_else(|| tracks.tracks.first())
+    else {
+        warn!("Reloaded {TRACKS_PATH} has no tracks, keeping \"{}\"", old.track_name);
+        return;
+    };
+    if new == old {
+        return;
+    }
+
+    for change in describe_changes(old, new) {
+        info!("Reloaded track \"{}\": {change}", new.track_name);
+    }
+    if let (Some(old_table), Some(new_table)) = (old.arc_length_table(), new.arc_length_table()) {
+        for (mut transform, progress) in &mut cars {
+            let position = old_table.closest_point(transform.translation.xy());
+            // Keep the same fraction of the track, in case it got longer or shorter.
+            let fraction = position.distance / old_table.length().max(f32::EPSILON);
+            let distance = fraction * new_table.length();
+            if let Some(mut progress) = progress {
+                progress.remeasure_from(distance);
+            }
+            let half_width = new.width_at(new_table.t_at_distance(distance)) / 2.0;
+            let lateral_offset = position.lateral_offset.clamp(-half_width, half_width);
+
+            let turn = old_table
+                .tangent_at(position.distance)
+                .angle_to(new_table.tangent_at(distance));
+            let translation = new_table.position_at(distance)
+                + new_table.normal_at(distance) * lateral_offset;
+            transform.translation = translation.extend(transform.translation.z);
+            transform.rotate_z(turn);
+        }
+    }
+    current_track.0 = Some(new.clone());
+}
+
+/// Measures the [`CurrentTrack`] for progress whenever it changes.
+fn update_race_course(current_track: Res<CurrentTrack>, mut current_race: ResMut<CurrentRace>) {
+    if current_track.is_changed() {
+        let laps = current_race.laps;
+        current_race.course = current_track
+            .0
+            .as_ref()
+            .and_then(|track| RaceCourse::new(track, laps));
+    }
+}
+
+fn update_race_progress(
+    current_race: Res<CurrentRace>,
+    mut cars: Query<(&Transform, &mut RaceProgress)>,
+) {
+    let Some(course) = &current_race.course else {
+        return;
+    };
+    for (transform, mut progress) in &mut cars {
+        course.advance(&mut progress, transform.translation.xy());
+    }
+}
+
+/// Ends the race once the player has finished it, at the end of the last lap or of the sprint,
+/// and scores it for the [`Championship`] under way.
+fn finish_race(
+    current_race: Res<CurrentRace>,
+    players: Query<&RaceProgress, (With<Player>, Changed<RaceProgress>)>,
+    championship: Option<ResMut<Championship>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if !players.iter().any(|progress| progress.finished) {
+        return;
+    }
+    info!("{PLAYER_ENTRANT} finished the race");
+    if let (true, Some(mut championship)) = (current_race.championship, championship) {
+        championship.finish_race(&[PLAYER_ENTRANT]);
+    }
+    next_screen.set(Screen::Title);
+}
+
+/// Short descriptions of what differs between two versions of a track.
+fn describe_changes(old: &RaceTrack, new: &RaceTrack) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.track_name != new.track_name {
+        changes.push(format!("renamed from \"{}\"", old.track_name));
+    }
+    if old.points.len() != new.points.len() {
+        changes.push(format!(
+            "{} control points, was {}",
+            new.points.len(),
+            old.points.len()
+        ));
+    } else {
+        let moved = old
+            .points
+            .iter()
+            .zip(&new.points)
+            .filter(|(a, b)| a != b)
+            .count();
+        if moved > 0 {
+            changes.push(format!("{moved} control points moved"));
+        }
+    }
+    if old.widths != new.widths {
+        changes.push("widths changed".to_string());
+    }
+    if old.closed != new.closed {
+        let shape = if new.closed { "a loop" } else { "point to point" };
+        changes.push(format!("now {shape}"));
+    }
+    if old.start_line != new.start_line {
+        changes.push("start/finish line moved".to_string());
+    }
+    if old.finish_line != new.finish_line {
+        changes.push("finish line moved".to_string());
+    }
+    if old.checkpoints != new.checkpoints {
+        changes.push(format!(
+            "{} checkpoints, was {}",
+            new.checkpoints.len(),
+            old.checkpoints.len()
+        ));
+    }
+    if old.grid_size != new.grid_size {
+        changes.push(format!("grid of {}, was {}", new.grid_size, old.grid_size));
+    }
+    if old.bridges != new.bridges {
+        changes.push(format!(
+            "{} bridges, was {}",
+            new.bridges.len(),
+            old.bridges.len()
+        ));
+    }
+    if old.props != new.props {
+        changes.push(format!("{} props, was {}", new.props.len(), old.props.len()));
+    }
+    if old.surfaces != new.surfaces {
+        changes.push(format!(
+            "{} surface zones, was {}",
+            new.surfaces.len(),
+            old.surfaces.len()
+        ));
+    }
+    if old.corner_names != new.corner_names {
+        changes.push(format!(
+            "{} named corners, was {}",
+            new.corner_names.len(),
+            old.corner_names.len()
+        ));
+    }
+    changes
+}
+
 pub fn instantiate_track(
     current_track: Res<CurrentTrack>,
     mut commands: Commands,
@@ -86,72 +425,150 @@ pub fn instantiate_track(
     for mesh in mesh_query.iter_mut() {
         commands.entity(mesh).despawn();
     }
+
+    for issue in track.validate() {
+        warn!("Track \"{}\": {issue}", track.track_name);
+    }
     
-    let bounds = track.get_bounds();
+    let Some(edges) = track.edges(TRACK_SAMPLE_SPACING) else {
+        return;
+    };
+    let mesh = build_track_mesh(
+        &edges,
+        &track.surfaces,
+        &TrackMeshSettings {
+            vertex_color: Some(GRAY.into()),
+            ..default()
+        },
+    );
 
-    for (i, (p0, p1)) in bounds.iter().enumerate() {
-        let mut mesh = Mesh::new(
-            PrimitiveTopology::TriangleList,
-            RenderAssetUsages::RENDER_WORLD,
-        );
-        /*
-        our triangles are, maybe?
-        p2---p3
-        p0
-             p3
-        p0---p1
-
-
-         */
-        let (p2, p3) = if i == bounds.len() - 1 {
-            &bounds[0]
-        } else {
-            &bounds[i + 1]
-        };
-
-        let vertices = vec![
-            [p0.x, p0.y, 0.0], //0
-            [p1.x, p1.y, 0.0], //1
-            [p2.x, p2.y, 0.0], //2
-            [p3.x, p3.y, 0.0], //3
-        ];
-        let color = LinearRgba::from(GRAY);
-        let colors = vertices
-            .iter()
-            .map(|_| [color.red, color.green, color.blue, color.alpha])
-            .collect::<Vec<_>>();
+    commands.spawn((
+        Name::new("Road"),
+        TrackPart,
+        RoadSensor,
+        RigidBody::Static,
+        Sensor,
+        road_collider(&edges),
+        TrackLayer::road_sensor(),
+        Mesh2d(meshes.add(mesh)),
+        MeshMaterial2d(materials.add(Color::WHITE)),
+    ));
+    for (layer, zone) in track.surfaces.iter().enumerate() {
+        commands.spawn((
+            Name::new("Surface Zone"),
+            TrackPart,
+            SurfaceSensor {
+                kind: zone.kind,
+                layer,
+            },
+            RigidBody::Static,
+            Sensor,
+            surface_collider(&edges, zone),
+            TrackLayer::road_sensor(),
+        ));
+    }
+    for (wall, layers) in wall_colliders(&edges, &track.bridges) {
+        commands.spawn((Name::new("Wall"), TrackPart, RigidBody::Static, wall, layers));
+    }
 
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    if let Some(table) = track.arc_length_table() {
+        for prop in &track.props {
+            let level = track.level_at(prop.distance, table.length());
+            let mut transform = prop.transform(&table);
+            transform.translation.z = level.z();
+            let size = prop.kind.size();
+            let mesh = if prop.kind.is_round() {
+                meshes.add(Circle::new(size.x / 2.0))
+            } else {
+                meshes.add(Rectangle::from_size(size))
+            };
+            commands.spawn((
+                Name::new(prop.kind.name()),
+                TrackPart,
+                RigidBody::Static,
+                prop.kind.collider(),
+                level.wall_layers(),
+                Mesh2d(mesh),
+                MeshMaterial2d(materials.add(prop.kind.color())),
+                transform,
+            ));
+        }
+    }
 
-        let indices = vec![
-            0, 2, 3,
-            0, 1, 3];
-        mesh.insert_indices(Indices::U32(indices));
-        
+    let bridge_settings = TrackMeshSettings {
+        vertex_color: Some(SILVER.into()),
+        ..default()
+    };
+    for bridge in &track.bridges {
+        let ramp = BRIDGE_RAMP_LENGTH;
         commands.spawn((
+            Name::new("Bridge"),
             TrackPart,
+            BridgeSpan,
             RigidBody::Static,
-            Collider::convex_hull(vec![*p0, *p2, *p3, *p1]).unwrap(),
-            Mesh2d(meshes.add(mesh)),
-            MeshMaterial2d(materials.add(Color::from(GRAY))),
+            Sensor,
+            span_collider(&edges, bridge.start, bridge.end),
+            TrackLayer::road_sensor(),
+            Mesh2d(meshes.add(build_bridge_mesh(&edges, bridge, &bridge_settings))),
+            MeshMaterial2d(materials.add(Color::WHITE)),
+            Transform::from_xyz(0.0, 0.0, BRIDGE_Z),
         ));
+        for (start, end) in [(bridge.start, bridge.start + ramp), (bridge.end - ramp, bridge.end)] {
+            commands.spawn((
+                Name::new("Bridge Ramp"),
+                TrackPart,
+                BridgeRamp,
+                RigidBody::Static,
+                Sensor,
+                span_collider(&edges, start, end),
+                TrackLayer::road_sensor(),
+            ));
+        }
     }
 }
 
-/// This system uses gizmos to draw the current [`Curves`] by breaking it up into a large number
-/// of line segments.
-fn draw_curve(curve: Res<Curves>, mut gizmos: Gizmos) {
-    let Some(ref center_curve) = curve.0 else {
+fn toggle_racing_line(mut show_racing_line: ResMut<ShowRacingLine>) {
+    show_racing_line.0 = !show_racing_line.0;
+}
+
+/// Draws the racing line through the current track, if the assist is switched on.
+fn draw_racing_line(
+    current_track: Res<CurrentTrack>,
+    show_racing_line: Res<ShowRacingLine>,
+    mut racing_lines: ResMut<RacingLineCache>,
+    mut gizmos: Gizmos,
+) {
+    if !show_racing_line.0 {
+        return;
+    }
+    let Some(line) = current_track.0.as_ref().and_then(|track| racing_lines.get(track)) else {
         return;
     };
-    // Scale resolution with curve length so it doesn't degrade as the length increases.
-    let resolution = 100 * center_curve.segments().len();
-    //Modify this to insert race track sections!
-    gizmos.linestrip(
-        center_curve
-            .iter_positions(resolution)
-            .map(|pt| pt.extend(0.0)),
-        Color::srgb(1.0, 1.0, 1.0),
-    );
+    line.draw(&mut gizmos, Color::srgb(0.2, 1.0, 0.3));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::racing::corners::CornerName;
+
+    #[test]
+    fn every_change_to_a_track_is_described() {
+        let old = RaceTrack::default();
+        let changes: [fn(&mut RaceTrack); 3] = [
+            |track| track.closed = !track.closed,
+            |track| track.finish_line += 1.0,
+            |track| {
+                track.corner_names.push(CornerName {
+                    distance: 100.0,
+                    name: "Hairpin".to_string(),
+                })
+            },
+        ];
+        for change in changes {
+            let mut new = old.clone();
+            change(&mut new);
+            assert!(!describe_changes(&old, &new).is_empty(), "{new:?}");
+        }
+    }
 }
@@ -13,9 +13,10 @@
 //! purposes. If you want to move the player in a smoother way,
 //! consider using a [fixed timestep](https://github.com/bevyengine/bevy/blob/main/examples/movement/physics_in_fixed_timestep.rs).
 
+use avian2d::prelude::LinearVelocity;
 use bevy::{prelude::*, window::PrimaryWindow};
 
-use crate::{AppSystems, PausableSystems};
+use crate::{AppSystems, PausableSystems, racing::surface::CurrentSurface};
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<MovementController>();
@@ -42,6 +43,10 @@ pub struct MovementController {
     /// Maximum speed in world units per second.
     /// 1 world unit = 1 pixel when using the default 2D camera and no physics engine.
     pub max_speed: f32,
+
+    /// The velocity the character is actually moving with, which catches up with the intent
+    /// faster the more grip there is.
+    pub velocity: Vec2,
 }
 
 impl Default for MovementController {
@@ -50,17 +55,30 @@ impl Default for MovementController {
             intent: Vec2::ZERO,
             // 400 pixels per second is a nice default, but we can still vary this per character.
             max_speed: 400.0,
+            velocity: Vec2::ZERO,
         }
     }
 }
 
+/// How quickly, per second, velocity catches up with the intent on full grip.
+const GRIP_RESPONSE: f32 = 12.0;
+
+/// Drives each character through the physics engine, so walls and other cars push back. The
+/// velocity carries on from what the physics left of it, which is nothing after a head-on crash.
 fn apply_movement(
     time: Res<Time>,
-    mut movement_query: Query<(&MovementController, &mut Transform)>,
+    mut movement_query: Query<(
+        &mut MovementController,
+        &mut LinearVelocity,
+        Option<&CurrentSurface>,
+    )>,
 ) {
-    for (controller, mut transform) in &mut movement_query {
-        let velocity = controller.max_speed * controller.intent;
-        transform.translation += velocity.extend(0.0) * time.delta_secs();
+    for (mut controller, mut linear_velocity, surface) in &mut movement_query {
+        let surface = surface.copied().unwrap_or_default();
+        let target = controller.max_speed * surface.top_speed() * controller.intent;
+        let response = (GRIP_RESPONSE * surface.grip() * time.delta_secs()).min(1.0);
+        controller.velocity = linear_velocity.0.lerp(target, response);
+        linear_velocity.0 = controller.velocity;
     }
 }
 
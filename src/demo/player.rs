@@ -1,5 +1,6 @@
 //! Player-specific behavior.
 
+use avian2d::prelude::{Collider, CollidingEntities, LockedAxes, RigidBody};
 use bevy::{
     image::{ImageLoaderSettings, ImageSampler},
     prelude::*,
@@ -12,6 +13,12 @@ use crate::{
         animation::PlayerAnimation,
         movement::{MovementController, ScreenWrap},
     },
+    racing::{
+        bridge::TrackLevel,
+        physics::{Car, OnRoad, TrackLayer},
+        progress::RaceProgress,
+        surface::CurrentSurface,
+    },
 };
 
 pub(super) fn plugin(app: &mut App) {
@@ -29,9 +36,13 @@ pub(super) fn plugin(app: &mut App) {
     );
 }
 
-/// The player character.
+/// The radius of the player's collider, before the sprite is scaled up.
+const PLAYER_RADIUS: f32 = 12.0;
+
+/// The player character, starting at `position`.
 pub fn player(
     max_speed: f32,
+    position: Vec2,
     player_assets: &PlayerAssets,
     texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
 ) -> impl Bundle {
@@ -52,19 +63,32 @@ pub fn player(
             }),
             ..default()
         },
-        Transform::from_scale(Vec2::splat(8.0).extend(1.0)),
+        Transform::from_translation(position.extend(0.0)).with_scale(Vec2::splat(8.0).extend(1.0)),
         MovementController {
             max_speed,
             ..default()
         },
         ScreenWrap,
         player_animation,
+        (
+            Car,
+            // Dynamic, so the walls stop it. The sprite stays upright.
+            RigidBody::Dynamic,
+            LockedAxes::ROTATION_LOCKED,
+            Collider::circle(PLAYER_RADIUS),
+            TrackLayer::car(),
+            CollidingEntities::default(),
+            OnRoad::default(),
+            TrackLevel::default(),
+            CurrentSurface::default(),
+            RaceProgress::default(),
+        ),
     )
 }
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
 #[reflect(Component)]
-struct Player;
+pub struct Player;
 
 fn record_player_directional_input(
     input: Res<ButtonInput<KeyCode>>,
@@ -15,7 +15,8 @@ fn spawn_main_menu(mut commands: Commands) {
         StateScoped(Menu::Main),
         #[cfg(not(target_family = "wasm"))]
         children![
-            widget::button("Play", enter_loading_or_gameplay_screen),
+            widget::button("Play", enter_loading_or_cup_select_screen),
+            widget::button("Tracks", open_track_select),
             widget::button("Editor", open_editor),
             widget::button("Settings", open_settings_menu),
             widget::button("Credits", open_credits_menu),
@@ -23,7 +24,8 @@ fn spawn_main_menu(mut commands: Commands) {
         ],
         #[cfg(target_family = "wasm")]
         children![
-            widget::button("Play", enter_loading_or_gameplay_screen),
+            widget::button("Play", enter_loading_or_cup_select_screen),
+            widget::button("Tracks", open_track_select),
             widget::button("Editor", open_editor),
             widget::button("Settings", open_settings_menu),
             widget::button("Credits", open_credits_menu),
@@ -31,13 +33,13 @@ fn spawn_main_menu(mut commands: Commands) {
     ));
 }
 
-fn enter_loading_or_gameplay_screen(
+fn enter_loading_or_cup_select_screen(
     _: Trigger<Pointer<Click>>,
     resource_handles: Res<ResourceHandles>,
     mut next_screen: ResMut<NextState<Screen>>,
 ) {
     if resource_handles.is_all_done() {
-        next_screen.set(Screen::Gameplay);
+        next_screen.set(Screen::CupSelect);
     } else {
         next_screen.set(Screen::Loading);
     }
@@ -52,6 +54,10 @@ fn open_editor(_: Trigger<Pointer<Click>>,
         next_screen.set(Screen::Editor);
 }
 
+fn open_track_select(_: Trigger<Pointer<Click>>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::TrackSelect);
+}
+
 fn open_credits_menu(_: Trigger<Pointer<Click>>, mut next_menu: ResMut<NextState<Menu>>) {
     next_menu.set(Menu::Credits);
 }
@@ -0,0 +1,372 @@
+//! Arc-length parameterization of a track's centerline.
+//!
+//! A [`CubicCurve`] moves at very different speeds over its segments, so sampling it uniformly in
+//! `t` bunches points on short segments and spreads them on long ones. [`ArcLengthTable`] maps
+//! distances along the centerline to curve parameters, which is what lap progress, AI and anything
+//! drawing the track actually wants.
+
+use bevy::math::Vec2;
+use bevy::prelude::CubicCurve;
+
+use super::RaceTrack;
+
+/// How many chords each curve segment is split into when measuring it.
+pub const ARC_LENGTH_SAMPLES_PER_SEGMENT: usize = 32;
+
+/// The spacing, in world units, between samples of the track used for meshes and colliders.
+pub const TRACK_SAMPLE_SPACING: f32 = 10.0;
+
+/// A position expressed relative to the track instead of the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPosition {
+    /// The distance along the centerline from the start of the curve.
+    pub distance: f32,
+    /// The signed distance from the centerline, positive to the left of the direction of travel.
+    pub lateral_offset: f32,
+}
+
+/// Where a new control point would go to split the centerline at a position, see
+/// [`RaceTrack::insertion_near`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointInsertion {
+    /// The index the new point gets, between the control points at either end of the segment.
+    pub index: usize,
+    /// The point on the centerline.
+    pub point: Vec2,
+    /// The width of the road there.
+    pub width: f32,
+}
+
+/// A lookup table between distance along a curve and its curve parameter `t`.
+#[derive(Debug, Clone)]
+pub struct ArcLengthTable {
+    curve: CubicCurve<Vec2>,
+    /// Whether the end of the curve joins up with its start.
+    closed: bool,
+    /// The curve parameter of every sample, evenly spaced in `t`.
+    params: Vec<f32>,
+    /// The distance along the curve at every sample. The last one is the total length.
+    distances: Vec<f32>,
+}
+
+impl ArcLengthTable {
+    pub fn new(curve: CubicCurve<Vec2>, closed: bool) -> Self {
+        let segments = curve.segments().len();
+        let sample_count = segments * ARC_LENGTH_SAMPLES_PER_SEGMENT;
+        let params = (0..=sample_count)
+            .map(|i| i as f32 / ARC_LENGTH_SAMPLES_PER_SEGMENT as f32)
+            .collect::<Vec<_>>();
+
+        let mut distances = Vec::with_capacity(params.len());
+        let mut distance = 0.0;
+        let mut previous = curve.position(0.0);
+        for &t in &params {
+            let position = curve.position(t);
+            distance += position.distance(previous);
+            distances.push(distance);
+            previous = position;
+        }
+
+        Self {
+            curve,
+            closed,
+            params,
+            distances,
+        }
+    }
+
+    pub fn curve(&self) -> &CubicCurve<Vec2> {
+        &self.curve
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Wraps a curve parameter around a closed curve, or clamps it to the ends of an open one.
+    pub fn wrap_t(&self, t: f32) -> f32 {
+        let segments = self.curve.segments().len() as f32;
+        if self.closed {
+            t.rem_euclid(segments)
+        } else {
+            t.clamp(0.0, segments)
+        }
+    }
+
+    /// The total length of the centerline.
+    pub fn length(&self) -> f32 {
+        self.distances.last().copied().unwrap_or_default()
+    }
+
+    /// Wraps a distance around a closed curve, or clamps it to the ends of an open one.
+    pub fn wrap_distance(&self, distance: f32) -> f32 {
+        let length = self.length();
+        if length <= 0.0 {
+            return 0.0;
+        }
+        if self.closed {
+            distance.rem_euclid(length)
+        } else {
+            distance.clamp(0.0, length)
+        }
+    }
+
+    /// The curve parameter at `distance` along the centerline.
+    pub fn t_at_distance(&self, distance: f32) -> f32 {
+        let distance = self.wrap_distance(distance);
+        let index = self
+            .distances
+            .partition_point(|&d| d < distance)
+            .clamp(1, self.distances.len() - 1);
+
+        let (d0, d1) = (self.distances[index - 1], self.distances[index]);
+        let (t0, t1) = (self.params[index - 1], self.params[index]);
+        if d1 - d0 <= f32::EPSILON {
+            return t0;
+        }
+        t0 + (t1 - t0) * (distance - d0) / (d1 - d0)
+    }
+
+    /// The distance along the centerline at curve parameter `t`.
+    pub fn distance_at_t(&self, t: f32) -> f32 {
+        let scaled = self.wrap_t(t) * ARC_LENGTH_SAMPLES_PER_SEGMENT as f32;
+        let index = (scaled.floor() as usize).min(self.distances.len() - 2);
+        let fraction = scaled - index as f32;
+
+        self.distances[index] + (self.distances[index + 1] - self.distances[index]) * fraction
+    }
+
+    /// The position on the centerline at `distance`.
+    pub fn position_at(&self, distance: f32) -> Vec2 {
+        self.curve.position(self.t_at_distance(distance))
+    }
+
+    /// The direction of travel at `distance`.
+    pub fn tangent_at(&self, distance: f32) -> Vec2 {
+        self.curve
+            .velocity(self.t_at_distance(distance))
+            .normalize_or_zero()
+    }
+
+    /// The direction pointing to the left of the direction of travel at `distance`.
+    pub fn normal_at(&self, distance: f32) -> Vec2 {
+        self.tangent_at(distance).perp()
+    }
+
+    /// The signed curvature (one over the radius) at curve parameter `t`. Positive values turn
+    /// left, negative values turn right.
+    pub fn curvature_at_t(&self, t: f32) -> f32 {
+        let velocity = self.curve.velocity(t);
+        let speed = velocity.length();
+        if speed <= f32::EPSILON {
+            return f32::INFINITY;
+        }
+        velocity.perp_dot(self.curve.acceleration(t)) / (speed * speed * speed)
+    }
+
+    /// Curve parameters evenly spaced `spacing` apart along the centerline, starting at `t = 0`.
+    /// The spacing is adjusted slightly so the samples divide the curve evenly. On a closed curve
+    /// the end (which is the same point as the start) is not included, on an open one it is.
+    pub fn uniform_params(&self, spacing: f32) -> Vec<f32> {
+        let length = self.length();
+        let count = (length / spacing.max(f32::EPSILON)).round().max(3.0) as usize;
+        let step = length / count as f32;
+        let last = if self.closed { count - 1 } else { count };
+        (0..=last)
+            .map(|i| self.t_at_distance(i as f32 * step))
+            .collect()
+    }
+
+    /// The centerline sampled at [`ArcLengthTable::uniform_params`], as a polyline.
+    pub fn uniform_positions(&self, spacing: f32) -> Vec<Vec2> {
+        self.uniform_params(spacing)
+            .into_iter()
+            .map(|t| self.curve.position(t))
+            .collect()
+    }
+
+    /// Finds the point on the centerline closest to `position`.
+    pub fn closest_point(&self, position: Vec2) -> TrackPosition {
+        self.closest_point_where(position, |_| true)
+    }
+
+    /// Finds the point on the centerline closest to `position` among those no further than `range`
+    /// along it from `around`. Where the track crosses itself, this keeps to the branch a car was
+    /// on rather than jumping to the other one.
+    pub fn closest_point_near(&self, position: Vec2, around: f32, range: f32) -> TrackPosition {
+        let length = self.length();
+        let closed = self.closed;
+        self.closest_point_where(position, |distance| {
+            let offset = distance - around;
+            let offset = if closed && length > 0.0 {
+                (offset + length / 2.0).rem_euclid(length) - length / 2.0
+            } else {
+                offset
+            };
+            offset.abs() <= range
+        })
+    }
+
+    /// Finds the point on the centerline closest to `position` on the chords starting at a
+    /// distance `keep` accepts.
+    fn closest_point_where(&self, position: Vec2, keep: impl Fn(f32) -> bool) -> TrackPosition {
+        // Find the closest chord of the table first...
+        let mut best_t = 0.0;
+        let mut best_distance_squared = f32::INFINITY;
+        let mut previous = self.curve.position(self.params[0]);
+        for (index, window) in self.params.windows(2).enumerate() {
+            let next = self.curve.position(window[1]);
+            if !keep(self.distances[index]) {
+                previous = next;
+                continue;
+            }
+            let chord = next - previous;
+            let fraction = if chord.length_squared() > f32::EPSILON {
+                ((position - previous).dot(chord) / chord.length_squared()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let distance_squared = (previous + chord * fraction).distance_squared(position);
+            if distance_squared < best_distance_squared {
+                best_distance_squared = distance_squared;
+                best_t = window[0] + (window[1] - window[0]) * fraction;
+            }
+            previous = next;
+        }
+
+        // ...then polish the curve parameter with a few Newton steps on the squared distance.
+        let step = 1.0 / ARC_LENGTH_SAMPLES_PER_SEGMENT as f32;
+        let (min_t, max_t) = (best_t - step, best_t + step);
+        let mut t = best_t;
+        for _ in 0..3 {
+            let wrapped = self.wrap_t(t);
+            let offset = self.curve.position(wrapped) - position;
+            let velocity = self.curve.velocity(wrapped);
+            let acceleration = self.curve.acceleration(wrapped);
+            let slope = offset.dot(velocity);
+            let curvature = velocity.length_squared() + offset.dot(acceleration);
+            if curvature <= f32::EPSILON {
+                break;
+            }
+            t = (t - slope / curvature).clamp(min_t, max_t);
+        }
+
+        let t = self.wrap_t(t);
+        let normal = self.curve.velocity(t).normalize_or_zero().perp();
+        TrackPosition {
+            distance: self.distance_at_t(t),
+            lateral_offset: (position - self.curve.position(t)).dot(normal),
+        }
+    }
+}
+
+/// Whether `distance` is in the range from `start` to `end` along a closed centerline `length`
+/// long. A range ending before it begins runs across the start of the curve.
+pub fn distance_range_contains(start: f32, end: f32, distance: f32, length: f32) -> bool {
+    if length <= 0.0 {
+        return false;
+    }
+    let distance = distance.rem_euclid(length);
+    let (start, end) = (start.rem_euclid(length), end.rem_euclid(length));
+    if start <= end {
+        (start..end).contains(&distance)
+    } else {
+        distance >= start || distance < end
+    }
+}
+
+impl RaceTrack {
+    /// Measures the centerline, or `None` if the track has too few points for a curve.
+    pub fn arc_length_table(&self) -> Option<ArcLengthTable> {
+        self.form_curve()
+            .0
+            .map(|curve| ArcLengthTable::new(curve, self.closed))
+    }
+
+    /// Where a control point would be inserted to split the segment of the centerline closest to
+    /// `position`, or `None` if `position` is not on the road.
+    pub fn insertion_near(&self, position: Vec2) -> Option<PointInsertion> {
+        let table = self.arc_length_table()?;
+        let closest = table.closest_point(position);
+        let t = table.t_at_distance(closest.distance);
+        let point = table.position_at(closest.distance);
+        let width = self.width_at(t);
+        if point.distance(position) > width / 2.0 {
+            return None;
+        }
+        // The ends of an open track are not on a segment, clicks there extend the track instead.
+        if !self.closed && (t <= 0.0 || t >= self.max_t()) {
+            return None;
+        }
+        // Control point `i` is at `t = i`, so the segment under `t` starts at its whole part.
+        Some(PointInsertion {
+            index: (t.floor() as usize + 1).min(self.points.len()),
+            point,
+            width,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use bevy::math::vec2;
+
+    use super::*;
+
+    fn table(points: Vec<Vec2>, closed: bool) -> ArcLengthTable {
+        RaceTrack {
+            widths: vec![40.0; points.len()],
+            points,
+            closed,
+            ..Default::default()
+        }
+        .arc_length_table()
+        .unwrap()
+    }
+
+    #[test]
+    fn a_circle_is_as_long_as_its_circumference() {
+        let points = (0..16)
+            .map(|i| Vec2::from_angle(i as f32 * TAU / 16.0) * 100.0)
+            .collect();
+        let length = table(points, true).length();
+        assert!((length - TAU * 100.0).abs() < TAU, "{length}");
+    }
+
+    #[test]
+    fn uniform_samples_are_evenly_spaced_over_uneven_segments() {
+        // One long segment between two short ones.
+        let points = vec![vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(300.0, 0.0), vec2(400.0, 0.0)];
+        let table = table(points, false);
+        let samples = table.uniform_positions(TRACK_SAMPLE_SPACING);
+        let spacing = table.length() / (samples.len() - 1) as f32;
+        for pair in samples.windows(2) {
+            assert!((pair[0].distance(pair[1]) - spacing).abs() < 0.5, "{pair:?}");
+        }
+        for distance in [0.0, 50.0, 200.0, 390.0] {
+            let t = table.t_at_distance(distance);
+            assert!((table.distance_at_t(t) - distance).abs() < 0.5, "{distance}");
+        }
+    }
+
+    #[test]
+    fn the_closest_point_is_measured_along_and_across_the_track() {
+        let points = vec![vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(200.0, 0.0), vec2(300.0, 0.0)];
+        let table = table(points, false);
+        let closest = table.closest_point(vec2(150.0, 10.0));
+        assert!((closest.distance - 150.0).abs() < 0.5, "{closest:?}");
+        // Left of travel along +x is +y.
+        assert!((closest.lateral_offset - 10.0).abs() < 0.5, "{closest:?}");
+    }
+
+    #[test]
+    fn distance_ranges_wrap_across_the_start() {
+        assert!(distance_range_contains(10.0, 20.0, 15.0, 100.0));
+        assert!(!distance_range_contains(10.0, 20.0, 25.0, 100.0));
+        assert!(distance_range_contains(90.0, 10.0, 95.0, 100.0));
+        assert!(distance_range_contains(90.0, 10.0, 5.0, 100.0));
+        assert!(!distance_range_contains(90.0, 10.0, 50.0, 100.0));
+    }
+}
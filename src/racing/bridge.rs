@@ -0,0 +1,151 @@
+//! Bridges, which let a track cross over itself.
+//!
+//! A bridge is a stretch of the track on an upper level. It is drawn above the rest of the road,
+//! and its walls are on their own collision layer, so cars on the bridge and cars passing under it
+//! ignore each other and each other's walls. Seen from above a car under a bridge is on top of it,
+//! so a car only moves up a level by driving onto one of the [`BridgeRamp`]s at either end, and
+//! stays up for as long as it is on the [`BridgeSpan`].
+
+use avian2d::prelude::{CollidingEntities, CollisionLayers};
+use bevy::prelude::{Component, Query, Reflect, Transform, With};
+use serde::{Deserialize, Serialize};
+
+use super::RaceTrack;
+use super::arc_length::distance_range_contains;
+use super::physics::TrackLayer;
+
+/// How far onto the bridge, from either end, a car is taken up to the bridge level.
+pub const BRIDGE_RAMP_LENGTH: f32 = 30.0;
+
+/// The height the road of a bridge is drawn at.
+pub const BRIDGE_Z: f32 = 0.5;
+
+/// A stretch of the track on the upper level.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct Bridge {
+    /// The distance along the centerline where the bridge begins.
+    pub start: f32,
+    /// The distance along the centerline where the bridge ends.
+    pub end: f32,
+}
+
+impl Bridge {
+    /// Whether the bridge covers `distance` along a centerline `length` long.
+    pub fn contains(&self, distance: f32, length: f32) -> bool {
+        distance_range_contains(self.start, self.end, distance, length)
+    }
+}
+
+impl RaceTrack {
+    /// Whether `distance` along a centerline `length` long is on a bridge.
+    pub fn on_bridge(&self, distance: f32, length: f32) -> bool {
+        self.bridges
+            .iter()
+            .any(|bridge| bridge.contains(distance, length))
+    }
+
+    /// The level of the track at `distance` along a centerline `length` long.
+    pub fn level_at(&self, distance: f32, length: f32) -> TrackLevel {
+        if self.on_bridge(distance, length) {
+            TrackLevel::Bridge
+        } else {
+            TrackLevel::Ground
+        }
+    }
+
+    /// Removes the bridge over curve parameter `t`, or adds one from the control point before it
+    /// to the control point after it if there is none.
+    pub fn toggle_bridge(&mut self, t: f32) {
+        let Some(table) = self.arc_length_table() else {
+            return;
+        };
+        let (distance, length) = (table.distance_at_t(t), table.length());
+        let existing = self
+            .bridges
+            .iter()
+            .position(|bridge| bridge.contains(distance, length));
+        match existing {
+            Some(index) => {
+                self.bridges.remove(index);
+            }
+            None => self.bridges.push(Bridge {
+                start: table.distance_at_t(self.wrap_t(t - 1.0)),
+                end: table.distance_at_t(self.wrap_t(t + 1.0)),
+            }),
+        }
+    }
+}
+
+/// Marks the sensor covering the whole of a bridge.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct BridgeSpan;
+
+/// Marks the sensors at either end of a bridge, which take cars up to the bridge level.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct BridgeRamp;
+
+/// The level of the track a car is on. Needs [`CollidingEntities`] and [`CollisionLayers`] on the
+/// same entity.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackLevel {
+    #[default]
+    Ground,
+    Bridge,
+}
+
+impl TrackLevel {
+    /// The collision layers of a car on this level.
+    pub fn car_layers(self) -> CollisionLayers {
+        match self {
+            TrackLevel::Ground => TrackLayer::car(),
+            TrackLevel::Bridge => TrackLayer::bridge_car(),
+        }
+    }
+
+    /// The collision layers of walls and props on this level.
+    pub fn wall_layers(self) -> CollisionLayers {
+        match self {
+            TrackLevel::Ground => TrackLayer::wall(),
+            TrackLevel::Bridge => TrackLayer::bridge_wall(),
+        }
+    }
+
+    /// The height cars and props on this level are drawn at, just above the road they are on.
+    pub fn z(self) -> f32 {
+        match self {
+            TrackLevel::Ground => BRIDGE_Z / 2.0,
+            TrackLevel::Bridge => BRIDGE_Z * 1.5,
+        }
+    }
+}
+
+/// Moves cars between levels as they drive onto and off bridges, switching their collision layers
+/// and draw order to match.
+pub fn update_track_level(
+    mut cars: Query<(
+        &CollidingEntities,
+        &mut TrackLevel,
+        &mut CollisionLayers,
+        &mut Transform,
+    )>,
+    ramps: Query<(), With<BridgeRamp>>,
+    spans: Query<(), With<BridgeSpan>>,
+) {
+    for (colliding, mut level, mut layers, mut transform) in &mut cars {
+        let on_ramp = colliding.iter().any(|&entity| ramps.contains(entity));
+        let on_span = colliding.iter().any(|&entity| spans.contains(entity));
+        let new_level = match *level {
+            _ if on_ramp => TrackLevel::Bridge,
+            TrackLevel::Bridge if on_span => TrackLevel::Bridge,
+            _ => TrackLevel::Ground,
+        };
+
+        if *level != new_level {
+            *level = new_level;
+            *layers = new_level.car_layers();
+        }
+        if transform.translation.z != new_level.z() {
+            transform.translation.z = new_level.z();
+        }
+    }
+}
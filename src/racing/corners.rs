@@ -0,0 +1,370 @@
+//! Corners of a [`RaceTrack`], found from the curvature of its centerline.
+//!
+//! The centerline is sampled evenly and every run of samples turning the same way more tightly
+//! than [`CORNER_RADIUS`] is a corner, as long as it turns through at least [`MIN_CORNER_ANGLE`].
+//! Everything between corners counts as straight.
+//!
+//! Corners are numbered in the order they are driven from the start line, "Turn 1" to "Turn N".
+//! A corner can be given a name of its own with a [`CornerName`], which is pinned to a distance
+//! along the centerline rather than to a number, so it stays with its corner when corners are
+//! added or removed before it.
+
+use std::fmt;
+
+use bevy::prelude::Reflect;
+use serde::{Deserialize, Serialize};
+
+use super::RaceTrack;
+use super::arc_length::{ArcLengthTable, TRACK_SAMPLE_SPACING, distance_range_contains};
+
+/// Bends with a larger radius than this are taken flat out and do not count as corners.
+pub const CORNER_RADIUS: f32 = 250.0;
+
+/// Bends turning through less than this many radians are kinks rather than corners.
+pub const MIN_CORNER_ANGLE: f32 = 0.35;
+
+/// Corners turning through more than this many radians are hairpins, however wide they are.
+const HAIRPIN_ANGLE: f32 = 2.6;
+
+/// Corners tighter than these radii are hairpins, slow and medium corners respectively.
+const HAIRPIN_RADIUS: f32 = 40.0;
+const SLOW_RADIUS: f32 = 80.0;
+const MEDIUM_RADIUS: f32 = 150.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnDirection {
+    Left,
+    Right,
+}
+
+impl fmt::Display for TurnDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TurnDirection::Left => write!(f, "left"),
+            TurnDirection::Right => write!(f, "right"),
+        }
+    }
+}
+
+/// How hard a corner is to take, from flat out to a hairpin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CornerSeverity {
+    Fast,
+    Medium,
+    Slow,
+    Hairpin,
+}
+
+impl CornerSeverity {
+    /// The severity of a corner with the given radius at its apex, turning through `angle`
+    /// radians.
+    pub fn of(radius: f32, angle: f32) -> Self {
+        if angle >= HAIRPIN_ANGLE || radius < HAIRPIN_RADIUS {
+            CornerSeverity::Hairpin
+        } else if radius < SLOW_RADIUS {
+            CornerSeverity::Slow
+        } else if radius < MEDIUM_RADIUS {
+            CornerSeverity::Medium
+        } else {
+            CornerSeverity::Fast
+        }
+    }
+}
+
+impl fmt::Display for CornerSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CornerSeverity::Fast => write!(f, "fast"),
+            CornerSeverity::Medium => write!(f, "medium"),
+            CornerSeverity::Slow => write!(f, "slow"),
+            CornerSeverity::Hairpin => write!(f, "hairpin"),
+        }
+    }
+}
+
+/// A custom name for the corner at a distance along the centerline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct CornerName {
+    /// Any distance within the corner.
+    pub distance: f32,
+    pub name: String,
+}
+
+/// A corner of the track. All distances are along the centerline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Corner {
+    /// The position of the corner in the order they are driven, starting from 1.
+    pub number: usize,
+    /// Where the corner begins. On a closed track it may be after the exit, if the corner runs
+    /// across the start of the curve.
+    pub entry: f32,
+    /// Where the corner is tightest.
+    pub apex: f32,
+    /// Where the corner ends.
+    pub exit: f32,
+    pub direction: TurnDirection,
+    /// The radius of the corner at its apex.
+    pub radius: f32,
+    /// How far the corner turns, in radians.
+    pub angle: f32,
+    pub severity: CornerSeverity,
+}
+
+impl Corner {
+    /// Whether the corner covers `distance` along a centerline `length` long.
+    pub fn contains(&self, distance: f32, length: f32) -> bool {
+        // The exit is the last sample in the corner, so it belongs to it too.
+        distance_range_contains(self.entry, self.exit, distance, length)
+            || distance == self.exit
+    }
+
+    /// What the corner is called when it has no name of its own.
+    pub fn default_name(&self) -> String {
+        format!("Turn {}", self.number)
+    }
+}
+
+/// A curvature sample along the centerline.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    distance: f32,
+    curvature: f32,
+}
+
+impl Sample {
+    fn direction(&self) -> Option<TurnDirection> {
+        if self.curvature.abs() * CORNER_RADIUS <= 1.0 {
+            None
+        } else if self.curvature > 0.0 {
+            Some(TurnDirection::Left)
+        } else {
+            Some(TurnDirection::Right)
+        }
+    }
+}
+
+impl RaceTrack {
+    /// The corners of the track, numbered and in the order they are driven from the start line,
+    /// or nothing if the track has too few points for a curve.
+    pub fn corners(&self) -> Vec<Corner> {
+        let Some(table) = self.arc_length_table() else {
+            return Vec::new();
+        };
+        let mut corners = find_corners(&table);
+        if table.is_closed() {
+            let (start, length) = (table.distance_at_t(self.start_line), table.length());
+            corners.sort_by(|a, b| {
+                let a = (a.entry - start).rem_euclid(length);
+                let b = (b.entry - start).rem_euclid(length);
+                a.total_cmp(&b)
+            });
+        }
+        for (index, corner) in corners.iter_mut().enumerate() {
+            corner.number = index + 1;
+        }
+        corners
+    }
+
+    /// The corner covering `distance`, if any.
+    pub fn corner_at(&self, distance: f32) -> Option<Corner> {
+        let length = self.arc_length_table()?.length();
+        self.corners()
+            .into_iter()
+            .find(|corner| corner.contains(distance, length))
+    }
+
+    /// The name of `corner` on a centerline `length` long: its own name if it has been given one,
+    /// otherwise its number.
+    pub fn corner_name(&self, corner: &Corner, length: f32) -> String {
+        self.corner_names
+            .iter()
+            .find(|name| corner.contains(name.distance, length))
+            .map_or_else(|| corner.default_name(), |name| name.name.clone())
+    }
+
+    /// Gives the corner covering `distance` a name of its own, or takes its name away if `name` is
+    /// `None`. Does nothing if there is no corner there.
+    pub fn rename_corner(&mut self, distance: f32, name: Option<String>) {
+        let Some(length) = self.arc_length_table().map(|table| table.length()) else {
+            return;
+        };
+        let Some(corner) = self.corner_at(distance) else {
+            return;
+        };
+        self.corner_names
+            .retain(|existing| !corner.contains(existing.distance, length));
+        if let Some(name) = name {
+            self.corner_names.push(CornerName {
+                distance: corner.apex,
+                name,
+            });
+        }
+    }
+}
+
+/// The corners of the centerline measured by `table`, in the order they are driven from the start
+/// of the curve. They are not numbered yet.
+pub fn find_corners(table: &ArcLengthTable) -> Vec<Corner> {
+    let mut samples = table
+        .uniform_params(TRACK_SAMPLE_SPACING)
+        .into_iter()
+        .map(|t| Sample {
+            distance: table.distance_at_t(t),
+            curvature: table.curvature_at_t(t),
+        })
+        .collect::<Vec<_>>();
+    let Some(step) = samples.get(1).map(|sample| sample.distance) else {
+        return Vec::new();
+    };
+    // Start a closed track on a straight, so no corner is cut in two by the seam.
+    if table.is_closed() {
+        let first = samples
+            .iter()
+            .position(|sample| sample.direction().is_none())
+            .unwrap_or(0);
+        samples.rotate_left(first);
+    }
+
+    let mut corners = Vec::new();
+    let mut run: Vec<Sample> = Vec::new();
+    for sample in samples.into_iter().map(Some).chain([None]) {
+        let direction = sample.and_then(|sample| sample.direction());
+        let continues = direction.is_some()
+            && run
+                .first()
+                .is_some_and(|first| first.direction() == direction);
+        if !continues && !run.is_empty() {
+            corners.extend(corner_from_run(&run, step));
+            run.clear();
+        }
+        if let (Some(sample), Some(_)) = (sample, direction) {
+            run.push(sample);
+        }
+    }
+    corners
+}
+
+/// The corner made by a run of samples all turning the same way, if it turns far enough.
+fn corner_from_run(run: &[Sample], step: f32) -> Option<Corner> {
+    let first = run.first()?;
+    let last = run.last()?;
+    let direction = first.direction()?;
+    let apex = run
+        .iter()
+        .max_by(|a, b| a.curvature.abs().total_cmp(&b.curvature.abs()))?;
+    let angle = run
+        .iter()
+        .map(|sample| sample.curvature.abs() * step)
+        .sum::<f32>();
+    let radius = apex.curvature.abs().recip();
+    (angle >= MIN_CORNER_ANGLE).then(|| Corner {
+        number: 0,
+        entry: first.distance,
+        apex: apex.distance,
+        exit: last.distance,
+        direction,
+        radius,
+        angle,
+        severity: CornerSeverity::of(radius, angle),
+    })
+}
+
+/// The length of the longest stretch of track between corners, on a centerline `length` long.
+pub fn longest_straight(corners: &[Corner], length: f32, closed: bool) -> f32 {
+    let (Some(first), Some(last)) = (corners.first(), corners.last()) else {
+        return length;
+    };
+    let between = corners
+        .windows(2)
+        .map(|pair| (pair[1].entry - pair[0].exit).rem_euclid(length));
+    let ends = if closed {
+        vec![(first.entry - last.exit).rem_euclid(length)]
+    } else {
+        vec![first.entry, length - last.exit]
+    };
+    between.chain(ends).fold(0.0, f32::max)
+}
+
+/// The radius of the tightest of `corners`, if there are any.
+pub fn tightest_radius(corners: &[Corner]) -> Option<f32> {
+    corners
+        .iter()
+        .map(|corner| corner.radius)
+        .min_by(f32::total_cmp)
+}
+
+/// How fast a car with `lateral_acceleration` of sideways grip can take a bend of `radius`.
+pub fn cornering_speed(radius: f32, lateral_acceleration: f32) -> f32 {
+    (lateral_acceleration * radius).sqrt()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::math::{Vec2, vec2};
+
+    use super::*;
+
+    /// A straight, a left turn through a right angle with a radius of 100, and another straight.
+    fn right_angle() -> RaceTrack {
+        let mut points = (0..8).map(|i| vec2(i as f32 * 50.0, 0.0)).collect::<Vec<_>>();
+        points.extend((0..=4).map(|i| {
+            let angle = -FRAC_PI_2 + i as f32 * FRAC_PI_2 / 4.0;
+            vec2(400.0, 100.0) + Vec2::from_angle(angle) * 100.0
+        }));
+        points.extend((1..8).map(|i| vec2(500.0, 100.0 + i as f32 * 50.0)));
+        RaceTrack {
+            widths: vec![40.0; points.len()],
+            points,
+            closed: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_bend_is_found_with_its_direction_and_severity() {
+        let corners = right_angle().corners();
+        assert_eq!(corners.len(), 1, "{corners:?}");
+        let corner = corners[0];
+        assert_eq!(corner.number, 1);
+        assert_eq!(corner.direction, TurnDirection::Left);
+        assert!((corner.angle - FRAC_PI_2).abs() < 0.3, "{corner:?}");
+        // The curve through the control points is a little tighter than the arc they sit on.
+        assert!((60.0..=100.0).contains(&corner.radius), "{corner:?}");
+        assert!(
+            matches!(corner.severity, CornerSeverity::Slow | CornerSeverity::Medium),
+            "{corner:?}"
+        );
+        assert!(corner.entry < corner.apex && corner.apex < corner.exit, "{corner:?}");
+    }
+
+    #[test]
+    fn a_named_corner_keeps_its_name_until_it_is_taken_away() {
+        let mut track = right_angle();
+        let length = track.arc_length_table().unwrap().length();
+        let corner = track.corners()[0];
+        assert_eq!(track.corner_name(&corner, length), "Turn 1");
+
+        track.rename_corner(corner.entry, Some("The Elbow".to_string()));
+        assert_eq!(track.corner_name(&corner, length), "The Elbow");
+        // Renaming again replaces the name rather than adding another.
+        track.rename_corner(corner.exit, Some("Elbow".to_string()));
+        assert_eq!(track.corner_names.len(), 1);
+        assert_eq!(track.corner_name(&corner, length), "Elbow");
+
+        track.rename_corner(corner.apex, None);
+        assert_eq!(track.corner_name(&corner, length), "Turn 1");
+        assert!(track.corner_names.is_empty());
+    }
+
+    #[test]
+    fn severity_goes_by_radius_and_angle() {
+        assert_eq!(CornerSeverity::of(200.0, 1.0), CornerSeverity::Fast);
+        assert_eq!(CornerSeverity::of(100.0, 1.0), CornerSeverity::Medium);
+        assert_eq!(CornerSeverity::of(60.0, 1.0), CornerSeverity::Slow);
+        assert_eq!(CornerSeverity::of(30.0, 1.0), CornerSeverity::Hairpin);
+        assert_eq!(CornerSeverity::of(200.0, 3.0), CornerSeverity::Hairpin);
+    }
+}
@@ -0,0 +1,342 @@
+//! Cups: championships raced over a list of tracks, read from `.cups` files.
+//!
+//! A cup lists its races in order, each on a track named in the track library and over a number of
+//! laps. After every race the finishers score points from the cup's points table, and its
+//! [`Qualification`] rule decides who goes on to the next race. A [`Championship`] keeps track of
+//! how far through a cup the player has got and of the standings so far.
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::math::FloatOrd;
+use bevy::prelude::{Asset, Reflect, Resource};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::library::TrackLibrary;
+
+/// The laps raced on a closed track when a cup does not say otherwise.
+pub const DEFAULT_LAPS: u32 = 3;
+
+/// The points for each finishing position, first place first, when a cup does not say otherwise.
+pub const DEFAULT_POINTS: [u32; 8] = [10, 8, 6, 5, 4, 3, 2, 1];
+
+#[derive(Debug, Clone, Default, Asset, Reflect, Deserialize, Serialize)]
+pub struct CupsAsset {
+    pub cups: Vec<Cup>,
+}
+
+impl CupsAsset {
+    pub fn get(&self, name: &str) -> Option<&Cup> {
+        self.cups.iter().find(|cup| cup.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Deserialize, Serialize)]
+pub struct Cup {
+    pub name: String,
+    /// The races of the cup, in the order they are raced.
+    pub races: Vec<CupRace>,
+    /// The points for each finishing position, first place first. Positions past the end of the
+    /// table score nothing.
+    #[serde(default = "default_points")]
+    pub points: Vec<u32>,
+    #[serde(default)]
+    pub qualification: Qualification,
+    /// Whether the races are put in order of difficulty when the cup starts, easiest first,
+    /// instead of being raced in the order they are listed.
+    #[serde(default)]
+    pub order_by_difficulty: bool,
+}
+
+impl Cup {
+    /// Puts the races in order of the difficulty of their tracks in `library`, easiest first.
+    /// Races on tracks that are not in the library go last.
+    pub fn sort_by_difficulty(&mut self, library: &TrackLibrary) {
+        self.races.sort_by_cached_key(|race| {
+            let difficulty = library
+                .find(&race.track_name)
+                .map_or(f32::INFINITY, |entry| entry.track.difficulty());
+            FloatOrd(difficulty)
+        });
+    }
+
+    /// The points scored for finishing in `position`, counting from zero for first place.
+    pub fn points_for(&self, position: usize) -> u32 {
+        self.points.get(position).copied().unwrap_or(0)
+    }
+}
+
+fn default_points() -> Vec<u32> {
+    DEFAULT_POINTS.to_vec()
+}
+
+/// One race of a [`Cup`].
+#[derive(Debug, Clone, PartialEq, Reflect, Deserialize, Serialize)]
+pub struct CupRace {
+    /// The name of the track in the track library.
+    pub track_name: String,
+    /// The laps raced, ignored on point-to-point tracks.
+    #[serde(default = "default_laps")]
+    pub laps: u32,
+}
+
+fn default_laps() -> u32 {
+    DEFAULT_LAPS
+}
+
+/// Who goes on to the next race of a cup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Deserialize, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Qualification {
+    /// Everyone races every race.
+    #[default]
+    All,
+    /// The top `count` finishers of each race qualify for the next one.
+    RaceTop { count: usize },
+    /// The top `count` in the standings after each race qualify for the next one.
+    StandingsTop { count: usize },
+}
+
+#[derive(Default)]
+pub struct CupsAssetLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum CupsAssetLoaderError {
+    /// An [IO](std::io) Error
+    #[error("Could not load asset: {0}")]
+    Io(#[from] std::io::Error),
+    /// A [JSON](serde_json) Error
+    #[error("Could not read cups file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Cup \"{0}\" has no races")]
+    NoRaces(String),
+    #[error("Cup \"{cup}\" races no laps on \"{track_name}\"")]
+    NoLaps { cup: String, track_name: String },
+}
+
+impl AssetLoader for CupsAssetLoader {
+    type Asset = CupsAsset;
+    type Settings = ();
+    type Error = CupsAssetLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let cups: CupsAsset = serde_json::from_slice(&bytes)?;
+        for cup in &cups.cups {
+            if cup.races.is_empty() {
+                return Err(CupsAssetLoaderError::NoRaces(cup.name.clone()));
+            }
+            if let Some(race) = cup.races.iter().find(|race| race.laps == 0) {
+                return Err(CupsAssetLoaderError::NoLaps {
+                    cup: cup.name.clone(),
+                    track_name: race.track_name.clone(),
+                });
+            }
+        }
+        Ok(cups)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cups"]
+    }
+}
+
+/// How one entrant is doing in a [`Championship`].
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub struct Standing {
+    pub entrant: String,
+    pub points: u32,
+    /// Whether the entrant takes part in the next race.
+    pub qualified: bool,
+}
+
+/// A cup in progress: the race up next and the standings so far.
+#[derive(Resource, Debug, Clone, Reflect)]
+pub struct Championship {
+    pub cup: Cup,
+    /// The position in the cup of the race up next. Equal to the number of races once the cup is
+    /// over.
+    pub race_index: usize,
+    /// Every entrant, leader first.
+    pub standings: Vec<Standing>,
+}
+
+impl Championship {
+    /// Starts `cup` with everyone in `entrants` qualified for the first race.
+    pub fn new(cup: Cup, entrants: impl IntoIterator<Item = String>) -> Self {
+        let standings = entrants
+            .into_iter()
+            .map(|entrant| Standing {
+                entrant,
+                points: 0,
+                qualified: true,
+            })
+            .collect();
+        Self {
+            cup,
+            race_index: 0,
+            standings,
+        }
+    }
+
+    /// The race up next, or `None` once the cup is over.
+    pub fn current_race(&self) -> Option<&CupRace> {
+        self.cup.races.get(self.race_index)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.race_index >= self.cup.races.len()
+    }
+
+    /// The entrants taking part in the race up next.
+    pub fn qualified(&self) -> impl Iterator<Item = &str> {
+        self.standings
+            .iter()
+            .filter(|standing| standing.qualified)
+            .map(|standing| standing.entrant.as_str())
+    }
+
+    pub fn is_qualified(&self, entrant: &str) -> bool {
+        self.qualified().any(|qualified| qualified == entrant)
+    }
+
+    pub fn leader(&self) -> Option<&Standing> {
+        self.standings.first()
+    }
+
+    /// Scores the race up next from its `finishing_order`, winner first, decides who qualifies for
+    /// the race after it and moves on to that race. Entrants who were not in the race or are
+    /// missing from the order score nothing.
+    pub fn finish_race(&mut self, finishing_order: &[&str]) {
+        if self.is_finished() {
+            return;
+        }
+
+        let finishers = finishing_order
+            .iter()
+            .copied()
+            .filter(|entrant| self.is_qualified(entrant))
+            .collect::<Vec<_>>();
+        for standing in &mut self.standings {
+            let position = finishers
+                .iter()
+                .position(|finisher| *finisher == standing.entrant);
+            standing.points += position.map_or(0, |position| self.cup.points_for(position));
+        }
+        // A stable sort keeps entrants on equal points in the order they were in before.
+        self.standings.sort_by_key(|standing| std::cmp::Reverse(standing.points));
+
+        match self.cup.qualification {
+            Qualification::All => {}
+            Qualification::RaceTop { count } => {
+                for standing in &mut self.standings {
+                    standing.qualified = finishers[..count.min(finishers.len())]
+                        .contains(&standing.entrant.as_str());
+                }
+            }
+            Qualification::StandingsTop { count } => {
+                let mut places = count;
+                for standing in &mut self.standings {
+                    if standing.qualified && places > 0 {
+                        places -= 1;
+                    } else {
+                        standing.qualified = false;
+                    }
+                }
+            }
+        }
+        self.race_index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cup(qualification: Qualification) -> Cup {
+        Cup {
+            name: "Test Cup".to_string(),
+            races: ["One", "Two", "Three"]
+                .map(|track_name| CupRace {
+                    track_name: track_name.to_string(),
+                    laps: DEFAULT_LAPS,
+                })
+                .to_vec(),
+            points: vec![10, 6, 3],
+            qualification,
+            order_by_difficulty: false,
+        }
+    }
+
+    fn championship(qualification: Qualification) -> Championship {
+        Championship::new(
+            cup(qualification),
+            ["A", "B", "C", "D"].map(str::to_string),
+        )
+    }
+
+    fn qualified(championship: &Championship) -> Vec<&str> {
+        let mut qualified = championship.qualified().collect::<Vec<_>>();
+        qualified.sort();
+        qualified
+    }
+
+    #[test]
+    fn finishers_score_and_the_leader_goes_first() {
+        let mut championship = championship(Qualification::All);
+        championship.finish_race(&["B", "A", "C", "D"]);
+        championship.finish_race(&["B", "C", "A"]);
+
+        let standings = championship
+            .standings
+            .iter()
+            .map(|standing| (standing.entrant.as_str(), standing.points))
+            .collect::<Vec<_>>();
+        assert_eq!(standings, [("B", 20), ("A", 9), ("C", 9), ("D", 0)]);
+        assert_eq!(championship.current_race().unwrap().track_name, "Three");
+        assert_eq!(qualified(&championship), ["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn the_top_of_each_race_goes_through() {
+        let mut championship = championship(Qualification::RaceTop { count: 2 });
+        championship.finish_race(&["C", "A", "D", "B"]);
+        assert_eq!(qualified(&championship), ["A", "C"]);
+
+        // Entrants knocked out cannot score, even if they are in the finishing order.
+        championship.finish_race(&["D", "A", "C"]);
+        assert_eq!(qualified(&championship), ["A", "C"]);
+        let d = championship
+            .standings
+            .iter()
+            .find(|standing| standing.entrant == "D")
+            .unwrap();
+        assert_eq!(d.points, 3);
+    }
+
+    #[test]
+    fn the_top_of_the_standings_goes_through() {
+        let mut championship = championship(Qualification::StandingsTop { count: 3 });
+        championship.finish_race(&["A", "B", "C", "D"]);
+        assert_eq!(qualified(&championship), ["A", "B", "C"]);
+
+        championship.finish_race(&["C", "B", "A"]);
+        // B has 12, A and C 13, and only those still in can stay in.
+        assert_eq!(qualified(&championship), ["A", "B", "C"]);
+        championship.finish_race(&["A", "B", "C"]);
+        assert!(championship.is_finished());
+        assert_eq!(championship.leader().unwrap().entrant, "A");
+
+        // A finished cup does not score any more races.
+        let standings = championship.standings.clone();
+        championship.finish_race(&["D"]);
+        assert_eq!(championship.standings, standings);
+    }
+}
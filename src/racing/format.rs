@@ -0,0 +1,391 @@
+//! Versioning of the `.tracks` file format.
+//!
+//! Every file written by the game carries a `format_version`. When an older file is loaded it is
+//! first parsed into a loose JSON document and then walked through [`MIGRATIONS`], one version at
+//! a time, until it matches [`CURRENT_FORMAT_VERSION`]. Only then is it deserialized into a
+//! [`TracksAsset`].
+//!
+//! Tracks are deserialized one at a time, so a single broken track does not take the rest of the
+//! file down with it. Broken tracks are kept verbatim in [`TracksAsset::broken_tracks`] and written
+//! back by [`to_string_pretty`], so they can be fixed by hand later.
+//!
+//! When changing the shape of [`TracksAsset`] or [`RaceTrack`], bump [`CURRENT_FORMAT_VERSION`]
+//! and append a migration that upgrades the previous version.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use super::{DEFAULT_TRACK_WIDTH, RaceTrack, TracksAsset, gates::DEFAULT_GRID_SIZE};
+
+/// The version written by [`to_string_pretty`] and expected by the rest of the game.
+pub const CURRENT_FORMAT_VERSION: u32 = 8;
+
+/// The key holding the version number in the root object of a `.tracks` file.
+pub const FORMAT_VERSION_KEY: &str = "format_version";
+
+/// The key holding the list of tracks in the root object of a `.tracks` file.
+const TRACKS_KEY: &str = "tracks";
+
+/// A migration upgrades the root object of a document by exactly one version.
+type Migration = fn(&mut Map<String, Value>);
+
+/// All migrations, indexed by the version they upgrade *from*. Files written before versioning
+/// was introduced have no version key and are treated as version 0.
+const MIGRATIONS: [Migration; CURRENT_FORMAT_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+];
+
+#[derive(Debug, Error)]
+pub enum FormatError {
+    /// The file is not valid JSON at all.
+    #[error("Could not parse JSON: {source}")]
+    Syntax {
+        line: usize,
+        column: usize,
+        source: serde_json::Error,
+    },
+    /// The file is valid JSON, but the root object does not match [`TracksAsset`].
+    #[error("Could not read tracks file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Expected a JSON object at the root of the tracks file")]
+    NotAnObject,
+    #[error("Expected a list of tracks under \"{}\"", TRACKS_KEY)]
+    MissingTracks,
+    #[error("Invalid format version: {0}")]
+    InvalidVersion(Value),
+    #[error(
+        "Format version {found} is newer than the supported version {}",
+        CURRENT_FORMAT_VERSION
+    )]
+    UnsupportedVersion { found: u32 },
+    /// Every track in the file failed to deserialize.
+    #[error("None of the tracks could be read:\n{}", list_broken_tracks(.0))]
+    NoValidTracks(Vec<BrokenTrack>),
+}
+
+/// A track that could not be deserialized, together with its original JSON.
+#[derive(Debug, Clone)]
+pub struct BrokenTrack {
+    /// The position of the track in the file.
+    pub index: usize,
+    /// The name of the track, if it could be read.
+    pub track_name: Option<String>,
+    pub error: String,
+    pub raw: Value,
+}
+
+impl fmt::Display for BrokenTrack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.track_name {
+            Some(name) => write!(f, "track #{} \"{}\": {}", self.index + 1, name, self.error),
+            None => write!(f, "track #{}: {}", self.index + 1, self.error),
+        }
+    }
+}
+
+fn list_broken_tracks(broken_tracks: &[BrokenTrack]) -> String {
+    broken_tracks
+        .iter()
+        .map(BrokenTrack::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses a `.tracks` document of any known version, migrating it to the current one.
+///
+/// Tracks that fail to deserialize are collected in [`TracksAsset::broken_tracks`]. If none of
+/// the tracks can be read, [`FormatError::NoValidTracks`] is returned instead.
+pub fn from_slice(bytes: &[u8]) -> Result<TracksAsset, FormatError> {
+    let document = serde_json::from_slice(bytes).map_err(|source| FormatError::Syntax {
+        line: source.line(),
+        column: source.column(),
+        source,
+    })?;
+    let mut document = migrate(document)?;
+
+    let Some(root) = document.as_object_mut() else {
+        return Err(FormatError::NotAnObject);
+    };
+    let Some(Value::Array(raw_tracks)) = root.remove(TRACKS_KEY) else {
+        return Err(FormatError::MissingTracks);
+    };
+
+    let mut tracks_asset: TracksAsset = serde_json::from_value(document)?;
+    for (index, raw) in raw_tracks.into_iter().enumerate() {
+        match serde_json::from_value::<RaceTrack>(raw.clone()) {
+            Ok(track) => tracks_asset.tracks.push(track),
+            Err(error) => tracks_asset.broken_tracks.push(BrokenTrack {
+                index,
+                track_name: raw
+                    .get("track_name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                error: error.to_string(),
+                raw,
+            }),
+        }
+    }
+
+    if tracks_asset.tracks.is_empty() && !tracks_asset.broken_tracks.is_empty() {
+        return Err(FormatError::NoValidTracks(tracks_asset.broken_tracks));
+    }
+    // The file counts the broken tracks too, which are not in the list the index points into.
+    let broken = &tracks_asset.broken_tracks;
+    let current = tracks_asset
+        .current_track_index
+        .filter(|index| broken.iter().all(|broken| broken.index != *index))
+        .map(|index| index - broken.iter().filter(|broken| broken.index < index).count())
+        .filter(|index| *index < tracks_asset.tracks.len());
+    tracks_asset.current_track_index = current;
+
+    Ok(tracks_asset)
+}
+
+/// Serializes the tracks, always stamping them with [`CURRENT_FORMAT_VERSION`].
+///
+/// Broken tracks are put back where they were in the file, unchanged, so that saving never loses
+/// them or moves them around.
+pub fn to_string_pretty(tracks_asset: &mut TracksAsset) -> serde_json::Result<String> {
+    tracks_asset.format_version = CURRENT_FORMAT_VERSION;
+    if tracks_asset.broken_tracks.is_empty() {
+        return serde_json::to_string_pretty(tracks_asset);
+    }
+
+    let mut document = serde_json::to_value(&*tracks_asset)?;
+    let mut current = tracks_asset.current_track_index;
+    if let Some(Value::Array(tracks)) = document.get_mut(TRACKS_KEY) {
+        let mut broken_tracks = tracks_asset.broken_tracks.iter().collect::<Vec<_>>();
+        broken_tracks.sort_by_key(|broken| broken.index);
+        for broken in broken_tracks {
+            let index = broken.index.min(tracks.len());
+            tracks.insert(index, broken.raw.clone());
+            current = current.map(|current| current + usize::from(index <= current));
+        }
+    }
+    if let Some(root) = document.as_object_mut() {
+        root.insert("current_track_index".to_string(), current.into());
+    }
+    serde_json::to_string_pretty(&document)
+}
+
+/// Reads the version of a document. A missing key means the file predates versioning.
+pub fn document_version(root: &Map<String, Value>) -> Result<u32, FormatError> {
+    match root.get(FORMAT_VERSION_KEY) {
+        None => Ok(0),
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| FormatError::InvalidVersion(value.clone())),
+    }
+}
+
+/// Runs every migration needed to bring `document` up to [`CURRENT_FORMAT_VERSION`].
+pub fn migrate(mut document: Value) -> Result<Value, FormatError> {
+    let Some(root) = document.as_object_mut() else {
+        return Err(FormatError::NotAnObject);
+    };
+
+    let mut version = document_version(root)?;
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(FormatError::UnsupportedVersion { found: version });
+    }
+
+    while version < CURRENT_FORMAT_VERSION {
+        MIGRATIONS[version as usize](root);
+        version += 1;
+        root.insert(FORMAT_VERSION_KEY.to_string(), Value::from(version));
+    }
+
+    Ok(document)
+}
+
+/// Version 1 only introduced the version key itself, which [`migrate`] writes.
+fn migrate_v0_to_v1(_root: &mut Map<String, Value>) {}
+
+/// Version 2 added a width per control point. Older tracks get the default width everywhere.
+fn migrate_v1_to_v2(root: &mut Map<String, Value>) {
+    for_each_track(root, |track| {
+        let point_count = track
+            .get("points")
+            .and_then(Value::as_array)
+            .map_or(0, Vec::len);
+        track
+            .entry("widths")
+            .or_insert_with(|| vec![DEFAULT_TRACK_WIDTH; point_count].into());
+    });
+}
+
+/// Version 3 added the start/finish line, checkpoints and the size of the starting grid. Older
+/// tracks start at the first control point and have no checkpoints.
+fn migrate_v2_to_v3(root: &mut Map<String, Value>) {
+    for_each_track(root, |track| {
+        track.entry("start_line").or_insert(Value::from(0.0));
+        track.entry("checkpoints").or_insert(Value::Array(Vec::new()));
+        track.entry("grid_size").or_insert(Value::from(DEFAULT_GRID_SIZE));
+    });
+}
+
+/// Version 4 added surface zones. Older tracks are plain asphalt all the way round.
+fn migrate_v3_to_v4(root: &mut Map<String, Value>) {
+    for_each_track(root, |track| {
+        track.entry("surfaces").or_insert(Value::Array(Vec::new()));
+    });
+}
+
+/// Version 5 added open tracks with a separate finish line. Every older track is a loop.
+fn migrate_v4_to_v5(root: &mut Map<String, Value>) {
+    for_each_track(root, |track| {
+        track.entry("closed").or_insert(Value::Bool(true));
+        track.entry("finish_line").or_insert(Value::from(0.0));
+    });
+}
+
+/// Version 6 added bridges. Older tracks are all on one level.
+fn migrate_v5_to_v6(root: &mut Map<String, Value>) {
+    for_each_track(root, |track| {
+        track.entry("bridges").or_insert(Value::Array(Vec::new()));
+    });
+}
+
+/// Version 7 added props. Older tracks have none.
+fn migrate_v6_to_v7(root: &mut Map<String, Value>) {
+    for_each_track(root, |track| {
+        track.entry("props").or_insert(Value::Array(Vec::new()));
+    });
+}
+
+/// Version 8 added custom corner names. Older tracks call every corner by its number.
+fn migrate_v7_to_v8(root: &mut Map<String, Value>) {
+    for_each_track(root, |track| {
+        track.entry("corner_names").or_insert(Value::Array(Vec::new()));
+    });
+}
+
+/// Applies `f` to every track object in the document, skipping anything that is not an object.
+fn for_each_track(root: &mut Map<String, Value>, mut f: impl FnMut(&mut Map<String, Value>)) {
+    let Some(Value::Array(tracks)) = root.get_mut(TRACKS_KEY) else {
+        return;
+    };
+    for track in tracks.iter_mut().filter_map(Value::as_object_mut) {
+        f(track);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+    use serde_json::json;
+
+    use super::*;
+
+    /// The track fields each version added, indexed by that version.
+    const ADDED_FIELDS: [&[&str]; CURRENT_FORMAT_VERSION as usize + 1] = [
+        &[],
+        &[],
+        &["widths"],
+        &["start_line", "checkpoints", "grid_size"],
+        &["surfaces"],
+        &["closed", "finish_line"],
+        &["bridges"],
+        &["props"],
+        &["corner_names"],
+    ];
+
+    fn named(track_name: &str) -> Value {
+        serde_json::to_value(RaceTrack {
+            track_name: track_name.to_string(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// A file with a broken track between two good ones.
+    fn partly_broken_file(current_track_index: Option<usize>) -> String {
+        json!({
+            "format_version": CURRENT_FORMAT_VERSION,
+            "tracks": [named("A"), { "track_name": "B", "points": "broken" }, named("C")],
+            "current_track_index": current_track_index,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn every_version_migrates_to_the_current_one() {
+        let track = RaceTrack {
+            track_name: "Old".to_string(),
+            points: vec![vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 100.0)],
+            widths: vec![42.0; 3],
+            closed: false,
+            start_line: 0.5,
+            finish_line: 1.5,
+            checkpoints: vec![1.0],
+            grid_size: 2,
+            ..Default::default()
+        };
+        let current = serde_json::to_value(&track).unwrap();
+
+        for version in 0..=CURRENT_FORMAT_VERSION {
+            let mut raw = current.clone();
+            for field in ADDED_FIELDS[version as usize + 1..].iter().copied().flatten() {
+                raw.as_object_mut().unwrap().remove(*field);
+            }
+            let mut document = json!({ "tracks": [raw] });
+            if version > 0 {
+                document[FORMAT_VERSION_KEY] = version.into();
+            }
+
+            // What the migrations fill in for the fields the file is missing.
+            let mut expected = track.clone();
+            if version < 2 {
+                expected.widths = vec![DEFAULT_TRACK_WIDTH; 3];
+            }
+            if version < 3 {
+                expected.start_line = 0.0;
+                expected.checkpoints = Vec::new();
+                expected.grid_size = DEFAULT_GRID_SIZE;
+            }
+            if version < 5 {
+                expected.closed = true;
+                expected.finish_line = 0.0;
+            }
+
+            let loaded = from_slice(document.to_string().as_bytes()).unwrap();
+            assert_eq!(loaded.format_version, CURRENT_FORMAT_VERSION);
+            assert_eq!(loaded.tracks, vec![expected], "from version {version}");
+        }
+    }
+
+    #[test]
+    fn broken_tracks_are_skipped_and_saved_back_in_place() {
+        let mut loaded = from_slice(partly_broken_file(Some(2)).as_bytes()).unwrap();
+        let names = loaded
+            .tracks
+            .iter()
+            .map(|track| track.track_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["A", "C"]);
+        assert_eq!(loaded.current_track_index, Some(1));
+        assert_eq!(loaded.broken_tracks.len(), 1);
+        assert_eq!(loaded.broken_tracks[0].index, 1);
+        assert_eq!(loaded.broken_tracks[0].track_name.as_deref(), Some("B"));
+
+        let saved: Value = serde_json::from_str(&to_string_pretty(&mut loaded).unwrap()).unwrap();
+        let original: Value = serde_json::from_str(&partly_broken_file(Some(2))).unwrap();
+        assert_eq!(saved, original);
+    }
+
+    #[test]
+    fn a_broken_current_track_is_not_selected() {
+        let loaded = from_slice(partly_broken_file(Some(1)).as_bytes()).unwrap();
+        assert_eq!(loaded.current_track_index, None);
+    }
+}
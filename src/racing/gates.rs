@@ -0,0 +1,224 @@
+//! The start/finish line, checkpoint gates and the starting grid of a [`RaceTrack`].
+//!
+//! All of these are stored as curve parameters, where `t = i` is the `i`:th control point, so they
+//! follow the track when control points are moved. Closed tracks are raced in laps over the start
+//! line, open tracks once from the start line to a separate finish line, see [`RaceMode`].
+
+use bevy::math::{Quat, Vec2};
+use bevy::prelude::Transform;
+
+use super::{MIN_TRACK_WIDTH, RaceTrack};
+
+/// How many starting slots a track gets when nothing else has been set.
+pub const DEFAULT_GRID_SIZE: usize = 8;
+
+/// The distance along the track from the start line to the first row of the grid.
+pub const GRID_FIRST_ROW_DISTANCE: f32 = 20.0;
+
+/// The distance along the track between two rows of the grid.
+pub const GRID_ROW_SPACING: f32 = 40.0;
+
+/// How a race on a track is run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceMode {
+    /// Laps of a closed track, crossing the start line at the end of each.
+    Laps,
+    /// A single run of an open track, from the start line to the finish line.
+    Sprint,
+}
+
+/// A line across the track, such as the start/finish line or a checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackGate {
+    /// The curve parameter the gate sits at.
+    pub t: f32,
+    /// The centre of the gate, on the centerline of the track.
+    pub position: Vec2,
+    /// The direction of travel through the gate.
+    pub direction: Vec2,
+    /// The full width of the road at the gate.
+    pub width: f32,
+}
+
+impl TrackGate {
+    /// The two ends of the gate, on the left and right edge of the road.
+    pub fn edges(&self) -> (Vec2, Vec2) {
+        let half_width = self.direction.perp() * self.width / 2.0;
+        (self.position + half_width, self.position - half_width)
+    }
+}
+
+/// A place on the starting grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridSlot {
+    /// The starting position, 0 being pole position.
+    pub index: usize,
+    pub position: Vec2,
+    /// The direction a car in this slot faces, which is the direction of travel.
+    pub direction: Vec2,
+}
+
+impl GridSlot {
+    /// A transform placing something in this slot, facing along the track.
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position.extend(0.0))
+            .with_rotation(Quat::from_rotation_z(self.direction.to_angle()))
+    }
+}
+
+impl RaceTrack {
+    /// Wraps a curve parameter around a closed track, or clamps it to the ends of an open one.
+    pub fn wrap_t(&self, t: f32) -> f32 {
+        if self.closed {
+            t.rem_euclid(self.max_t().max(1.0))
+        } else {
+            t.clamp(0.0, self.max_t())
+        }
+    }
+
+    pub fn race_mode(&self) -> RaceMode {
+        if self.closed {
+            RaceMode::Laps
+        } else {
+            RaceMode::Sprint
+        }
+    }
+
+    /// The distance along the centerline a car covers in a race of `laps` laps. Sprints are always
+    /// run once, from the start line to the finish line.
+    pub fn race_length(&self, laps: u32) -> f32 {
+        let Some(table) = self.arc_length_table() else {
+            return 0.0;
+        };
+        match self.race_mode() {
+            RaceMode::Laps => table.length() * laps as f32,
+            RaceMode::Sprint => {
+                (table.distance_at_t(self.finish_line) - table.distance_at_t(self.start_line)).abs()
+            }
+        }
+    }
+
+    /// The gate at curve parameter `t`, or `None` if the track has too few points for a curve.
+    pub fn gate_at(&self, t: f32) -> Option<TrackGate> {
+        let curve = self.form_curve().0?;
+        let t = self.wrap_t(t);
+        Some(TrackGate {
+            t,
+            position: curve.position(t),
+            direction: curve.velocity(t).normalize_or_zero(),
+            width: self.width_at(t),
+        })
+    }
+
+    /// The start line, which is also the finish line on closed tracks.
+    pub fn start_line_gate(&self) -> Option<TrackGate> {
+        self.gate_at(self.start_line)
+    }
+
+    /// The line that ends the race.
+    pub fn finish_line_gate(&self) -> Option<TrackGate> {
+        match self.race_mode() {
+            RaceMode::Laps => self.start_line_gate(),
+            RaceMode::Sprint => self.gate_at(self.finish_line),
+        }
+    }
+
+    /// The checkpoint gates, in the order they have to be passed.
+    pub fn checkpoint_gates(&self) -> Vec<TrackGate> {
+        self.checkpoints
+            .iter()
+            .filter_map(|&t| self.gate_at(t))
+            .collect()
+    }
+
+    /// Moves the start/finish line to `t`, keeping the checkpoints in the order they are reached
+    /// from the new line.
+    pub fn set_start_line(&mut self, t: f32) {
+        self.start_line = self.wrap_t(t);
+        self.sort_checkpoints();
+    }
+
+    /// Moves the finish line of an open track to `t`.
+    pub fn set_finish_line(&mut self, t: f32) {
+        self.finish_line = self.wrap_t(t);
+    }
+
+    /// Switches between a closed loop and an open track. An open track gets its finish line at
+    /// the last control point unless it already has one after the start line.
+    pub fn toggle_closed(&mut self) {
+        self.closed = !self.closed;
+        if !self.closed && self.finish_line <= self.start_line {
+            self.finish_line = self.max_t();
+        }
+        self.start_line = self.wrap_t(self.start_line);
+        self.finish_line = self.wrap_t(self.finish_line);
+        self.checkpoints = self.checkpoints.iter().map(|&t| self.wrap_t(t)).collect();
+        self.sort_checkpoints();
+    }
+
+    /// Adds a checkpoint at `t`, or removes it if there already is one there.
+    pub fn toggle_checkpoint(&mut self, t: f32) {
+        let t = self.wrap_t(t);
+        let existing = self
+            .checkpoints
+            .iter()
+            .position(|&checkpoint| (checkpoint - t).abs() < f32::EPSILON);
+        match existing {
+            Some(index) => {
+                self.checkpoints.remove(index);
+            }
+            None => {
+                self.checkpoints.push(t);
+                self.sort_checkpoints();
+            }
+        }
+    }
+
+    /// Orders the checkpoints by how far after the start line they are.
+    fn sort_checkpoints(&mut self) {
+        let start_line = self.start_line;
+        let closed = self.closed;
+        let length = self.max_t().max(1.0);
+        let after_start = |t: f32| {
+            if closed {
+                (t - start_line).rem_euclid(length)
+            } else {
+                t - start_line
+            }
+        };
+        self.checkpoints
+            .sort_by(|&a, &b| after_start(a).total_cmp(&after_start(b)));
+    }
+
+    /// Generates the starting grid: [`RaceTrack::grid_size`] slots in two staggered columns behind
+    /// the start line, all facing the direction of travel. On an open track, slots that would be
+    /// behind the start of the road are bunched up at the start.
+    pub fn starting_grid(&self) -> Vec<GridSlot> {
+        let Some(table) = self.arc_length_table() else {
+            return Vec::new();
+        };
+        let start_distance = table.distance_at_t(self.start_line);
+
+        (0..self.grid_size)
+            .map(|index| {
+                let row = (index / 2) as f32;
+                let column = index % 2;
+                // The second column is staggered half a row back.
+                let distance = GRID_FIRST_ROW_DISTANCE
+                    + row * GRID_ROW_SPACING
+                    + column as f32 * GRID_ROW_SPACING / 2.0;
+                let t = table.t_at_distance(start_distance - distance);
+
+                let direction = table.curve().velocity(t).normalize_or_zero();
+                let lateral = self.width_at(t).max(MIN_TRACK_WIDTH) / 4.0;
+                let side = if column == 0 { 1.0 } else { -1.0 };
+
+                GridSlot {
+                    index,
+                    position: table.curve().position(t) + direction.perp() * lateral * side,
+                    direction,
+                }
+            })
+            .collect()
+    }
+}
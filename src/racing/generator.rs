@@ -0,0 +1,178 @@
+//! Seeded procedural generation of race tracks.
+//!
+//! The same seed and [`GeneratorSettings`] always produce the same [`RaceTrack`], so a seed is all
+//! that is needed to share a generated track, run a daily challenge or reproduce a bug.
+
+use std::f32::consts::TAU;
+
+use bevy::math::{Rect, Vec2};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use thiserror::Error;
+
+use super::RaceTrack;
+use super::arc_length::{ARC_LENGTH_SAMPLES_PER_SEGMENT, ArcLengthTable};
+use super::validation::self_intersections;
+
+/// Tracks with fewer corners than this are not much of a loop.
+pub const MIN_CORNER_COUNT: usize = 3;
+
+/// How many different layouts are tried for a seed before giving up.
+const MAX_ATTEMPTS: usize = 32;
+
+/// How many times a layout is smoothed out to bring its tightest corner within the limit.
+const MAX_SMOOTHING_PASSES: usize = 24;
+
+/// How far off the target length a generated track may end up, as a fraction of the target.
+const LENGTH_TOLERANCE: f32 = 0.2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorSettings {
+    /// The length of the centerline to aim for, in world units.
+    pub target_length: f32,
+    /// The number of control points, each of which becomes a corner of some sort.
+    pub corner_count: usize,
+    /// The curvature (one over the radius) of the tightest allowed corner.
+    pub max_curvature: f32,
+    /// The area the whole track, including its width, has to fit in.
+    pub bounds: Rect,
+    pub min_width: f32,
+    pub max_width: f32,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            target_length: 3000.0,
+            corner_count: 10,
+            max_curvature: 1.0 / 50.0,
+            bounds: Rect::new(-600.0, -340.0, 600.0, 340.0),
+            min_width: 30.0,
+            max_width: 60.0,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GeneratorError {
+    #[error("A track needs at least {MIN_CORNER_COUNT} corners, {0} were requested")]
+    TooFewCorners(usize),
+    #[error("No valid track found for seed {seed} after {MAX_ATTEMPTS} attempts")]
+    NoValidTrack { seed: u64 },
+}
+
+/// Generates a closed, non-self-intersecting track from `seed`.
+pub fn generate_track(seed: u64, settings: &GeneratorSettings) -> Result<RaceTrack, GeneratorError> {
+    if settings.corner_count < MIN_CORNER_COUNT {
+        return Err(GeneratorError::TooFewCorners(settings.corner_count));
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    for _ in 0..MAX_ATTEMPTS {
+        if let Some(track) = try_generate(&mut rng, settings) {
+            return Ok(RaceTrack {
+                track_name: format!("Generated {seed}"),
+                ..track
+            });
+        }
+    }
+    Err(GeneratorError::NoValidTrack { seed })
+}
+
+fn try_generate(rng: &mut ChaCha8Rng, settings: &GeneratorSettings) -> Option<RaceTrack> {
+    let count = settings.corner_count;
+    let center = settings.bounds.center();
+    // Keep the edges of the widest road inside the bounds.
+    let half_size = (settings.bounds.half_size() - settings.max_width).max(Vec2::ONE);
+
+    // One point per angular sector around the centre gives a star-shaped polygon, which never
+    // crosses itself and keeps the spline well behaved.
+    let sector = TAU / count as f32;
+    let start_angle = rng.random_range(0.0..TAU);
+    let mut points = (0..count)
+        .map(|i| {
+            let angle = start_angle + (i as f32 + rng.random_range(0.2..0.8)) * sector;
+            let radius = rng.random_range(0.4..1.0);
+            center + Vec2::from_angle(angle) * half_size * radius
+        })
+        .collect::<Vec<_>>();
+    let widths = (0..count)
+        .map(|_| rng.random_range(settings.min_width..=settings.max_width))
+        .collect::<Vec<_>>();
+
+    let mut track = RaceTrack {
+        widths,
+        ..Default::default()
+    };
+    for _ in 0..MAX_SMOOTHING_PASSES {
+        fit_to_length(&mut points, center, half_size, settings.target_length)?;
+        track.points = points.clone();
+        let table = track.arc_length_table()?;
+        if max_curvature(&table) <= settings.max_curvature {
+            let length_error = (table.length() - settings.target_length).abs();
+            let crosses = !self_intersections(&table).is_empty();
+            return (length_error <= settings.target_length * LENGTH_TOLERANCE && !crosses)
+                .then_some(track);
+        }
+        relax(&mut points);
+    }
+    None
+}
+
+/// Scales the points about `center` so the curve through them is `target_length` long, without
+/// leaving the bounds.
+fn fit_to_length(points: &mut [Vec2], center: Vec2, half_size: Vec2, target_length: f32) -> Option<()> {
+    let track = RaceTrack {
+        points: points.to_vec(),
+        ..Default::default()
+    };
+    let length = track.arc_length_table()?.length();
+    if length <= f32::EPSILON {
+        return None;
+    }
+
+    let largest_offset = points
+        .iter()
+        .map(|point| ((*point - center) / half_size).abs().max_element())
+        .fold(0.0, f32::max);
+    let scale = (target_length / length).min(1.0 / largest_offset.max(f32::EPSILON));
+
+    for point in points.iter_mut() {
+        *point = center + (*point - center) * scale;
+    }
+    Some(())
+}
+
+/// Moves every point a little towards the midpoint of its neighbours, rounding off sharp corners.
+fn relax(points: &mut [Vec2]) {
+    let original = points.to_vec();
+    let count = original.len();
+    for (i, point) in points.iter_mut().enumerate() {
+        let previous = original[(i + count - 1) % count];
+        let next = original[(i + 1) % count];
+        *point = point.lerp(previous.midpoint(next), 0.25);
+    }
+}
+
+/// The curvature of the tightest corner on the curve.
+fn max_curvature(table: &ArcLengthTable) -> f32 {
+    let samples = table.curve().segments().len() * ARC_LENGTH_SAMPLES_PER_SEGMENT;
+    (0..samples)
+        .map(|i| table.curvature_at_t(i as f32 / ARC_LENGTH_SAMPLES_PER_SEGMENT as f32).abs())
+        .fold(0.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_seed_always_generates_the_same_valid_track() {
+        let settings = GeneratorSettings::default();
+        for seed in [0, 1, 20261017, u64::MAX] {
+            let track = generate_track(seed, &settings).unwrap();
+            assert_eq!(track, generate_track(seed, &settings).unwrap());
+            assert!(track.is_valid(), "seed {seed}: {:?}", track.validate());
+        }
+    }
+}
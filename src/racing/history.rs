@@ -0,0 +1,483 @@
+//! Undo and redo for the track editor.
+//!
+//! Every change made in the editor is an [`EditCommand`], which can be applied and turned into its
+//! own inverse. Each track of a [`TracksAsset`] keeps a history of its own, so undoing on one
+//! track never takes back what was done on another. A command belongs to the track that is
+//! current once it has been applied, which makes switching tracks undoable like any other edit:
+//! undoing a switch goes back to the track it came from, and carries on with that track's history.
+//! Adding a track works the same way, and undoing it takes the track and its history out of the
+//! file again.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use bevy::math::Vec2;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::Resource;
+
+use super::{ControlPoints, RaceTrack, TracksAsset};
+
+/// How many commands each track remembers. The oldest are forgotten first.
+pub const HISTORY_SIZE: usize = 100;
+
+/// A change made in the editor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditCommand {
+    /// A control point added before the one at `index`. Adding a point at the end of the track
+    /// inserts it at the number of points.
+    InsertPoint { index: usize, point: Vec2, width: f32 },
+    DeletePoint { index: usize, point: Vec2, width: f32 },
+    MovePoint { index: usize, from: Vec2, to: Vec2 },
+    /// The road width at a control point changed.
+    SetWidth { index: usize, from: f32, to: f32 },
+    RenameTrack { from: String, to: String },
+    /// Another track of the file became the current one.
+    SwitchTrack { from: usize, to: usize },
+    /// A track added to the file at `index`, which became the current one in place of the track
+    /// at `from`.
+    AddTrack {
+        index: usize,
+        track: Box<RaceTrack>,
+        from: usize,
+    },
+    /// The track at `index` taken out of the file, along with its history, leaving the track at
+    /// `to` current.
+    RemoveTrack {
+        index: usize,
+        track: Box<RaceTrack>,
+        to: usize,
+    },
+    /// Any other change to the current track, remembered as the whole track before and after it.
+    EditTrack {
+        before: Box<RaceTrack>,
+        after: Box<RaceTrack>,
+    },
+    /// Several commands undone and redone together, applied in order.
+    Group(Vec<EditCommand>),
+}
+
+impl EditCommand {
+    /// The command that takes this one back.
+    pub fn inverse(&self) -> Self {
+        match self.clone() {
+            EditCommand::InsertPoint { index, point, width } => {
+                EditCommand::DeletePoint { index, point, width }
+            }
+            EditCommand::DeletePoint { index, point, width } => {
+                EditCommand::InsertPoint { index, point, width }
+            }
+            EditCommand::MovePoint { index, from, to } => EditCommand::MovePoint {
+                index,
+                from: to,
+                to: from,
+            },
+            EditCommand::SetWidth { index, from, to } => EditCommand::SetWidth {
+                index,
+                from: to,
+                to: from,
+            },
+            EditCommand::RenameTrack { from, to } => EditCommand::RenameTrack { from: to, to: from },
+            EditCommand::SwitchTrack { from, to } => EditCommand::SwitchTrack { from: to, to: from },
+            EditCommand::AddTrack { index, track, from } => EditCommand::RemoveTrack {
+                index,
+                track,
+                to: from,
+            },
+            EditCommand::RemoveTrack { index, track, to } => EditCommand::AddTrack {
+                index,
+                track,
+                from: to,
+            },
+            EditCommand::EditTrack { before, after } => EditCommand::EditTrack {
+                before: after,
+                after: before,
+            },
+            EditCommand::Group(commands) => {
+                EditCommand::Group(commands.iter().rev().map(EditCommand::inverse).collect())
+            }
+        }
+    }
+
+    /// Makes the change to the points being edited and to the tracks of the file.
+    pub fn apply(&self, control_points: &mut ControlPoints, tracks_asset: &mut TracksAsset) {
+        match self {
+            EditCommand::InsertPoint { index, point, width } => {
+                edit_points(control_points, tracks_asset, |track| {
+                    track.insert_point(*index, *point, *width);
+                });
+            }
+            EditCommand::DeletePoint { index, .. } => {
+                edit_points(control_points, tracks_asset, |track| track.remove_point(*index));
+            }
+            EditCommand::MovePoint { index, to, .. } => {
+                if let Some(point) = control_points.points.get_mut(*index) {
+                    *point = *to;
+                }
+            }
+            EditCommand::SetWidth { index, to, .. } => {
+                if let Some(width) = control_points.widths.get_mut(*index) {
+                    *width = *to;
+                }
+            }
+            EditCommand::RenameTrack { to, .. } => {
+                if let Some(track) = tracks_asset.get_current_track_mut() {
+                    track.track_name = to.clone();
+                }
+            }
+            EditCommand::SwitchTrack { to, .. } => {
+                // The track being left keeps the points as they were edited.
+                tracks_asset
+                    .update_current_track(control_points.points.clone(), control_points.widths.clone());
+                if *to < tracks_asset.tracks.len() {
+                    tracks_asset.current_track_index = Some(*to);
+                }
+                load_current_track(control_points, tracks_asset);
+                control_points.selected = None;
+            }
+            EditCommand::AddTrack { index, track, .. } => {
+                tracks_asset
+                    .update_current_track(control_points.points.clone(), control_points.widths.clone());
+                let index = (*index).min(tracks_asset.tracks.len());
+                tracks_asset.tracks.insert(index, (**track).clone());
+                tracks_asset.current_track_index = Some(index);
+                load_current_track(control_points, tracks_asset);
+                control_points.selected = None;
+            }
+            EditCommand::RemoveTrack { index, to, .. } => {
+                if *index < tracks_asset.tracks.len() {
+                    tracks_asset.tracks.remove(*index);
+                }
+                tracks_asset.current_track_index =
+                    (!tracks_asset.tracks.is_empty()).then(|| (*to).min(tracks_asset.tracks.len() - 1));
+                load_current_track(control_points, tracks_asset);
+                control_points.selected = None;
+            }
+            EditCommand::EditTrack { after, .. } => {
+                if let Some(track) = tracks_asset.get_current_track_mut() {
+                    *track = (**after).clone();
+                }
+                load_current_track(control_points, tracks_asset);
+            }
+            EditCommand::Group(commands) => {
+                for command in commands {
+                    command.apply(control_points, tracks_asset);
+                }
+            }
+        }
+    }
+}
+
+/// Adds or removes control points of the track being edited with `edit`, which keeps its gates,
+/// surfaces and everything else placed along it where they were on the road.
+fn edit_points(
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+    edit: impl FnOnce(&mut RaceTrack),
+) {
+    let mut track = tracks_asset.get_current_track().cloned().unwrap_or_default();
+    track.points = control_points.points.clone();
+    track.widths = control_points.widths.clone();
+    edit(&mut track);
+    control_points.points = track.points.clone();
+    control_points.widths = track.widths.clone();
+    control_points.selected = None;
+    if let Some(current) = tracks_asset.get_current_track_mut() {
+        *current = track;
+    }
+}
+
+/// Starts editing the points of the current track, keeping the selected point if it still exists.
+fn load_current_track(control_points: &mut ControlPoints, tracks_asset: &TracksAsset) {
+    let Some(track) = tracks_asset.get_current_track() else {
+        return;
+    };
+    control_points.points = track.points.clone();
+    control_points.widths = track.point_widths();
+    control_points.selected = control_points
+        .selected
+        .filter(|&selected| selected < control_points.points.len());
+}
+
+/// The commands done and undone on one track.
+#[derive(Debug, Default)]
+struct TrackHistory {
+    /// Oldest first.
+    undo: VecDeque<EditCommand>,
+    /// The most recently undone last. Cleared by any new command.
+    redo: Vec<EditCommand>,
+}
+
+impl TrackHistory {
+    fn push_undo(&mut self, command: EditCommand) {
+        self.undo.push_back(command);
+        if self.undo.len() > HISTORY_SIZE {
+            self.undo.pop_front();
+        }
+    }
+}
+
+/// The undo and redo history of every track in the file being edited, by track index.
+#[derive(Resource, Debug, Default)]
+pub struct EditHistory {
+    tracks: HashMap<usize, TrackHistory>,
+}
+
+impl EditHistory {
+    /// Applies `command` and remembers it.
+    pub fn perform(
+        &mut self,
+        command: EditCommand,
+        control_points: &mut ControlPoints,
+        tracks_asset: &mut TracksAsset,
+    ) {
+        self.apply(&command, control_points, tracks_asset);
+        self.record(command, tracks_asset);
+    }
+
+    /// Remembers `command`, which has already been applied.
+    pub fn record(&mut self, command: EditCommand, tracks_asset: &TracksAsset) {
+        let history = self.current(tracks_asset);
+        history.redo.clear();
+        history.push_undo(command);
+    }
+
+    /// Takes back the last command on the current track. Returns `false` if there was nothing to
+    /// undo.
+    pub fn undo(&mut self, control_points: &mut ControlPoints, tracks_asset: &mut TracksAsset) -> bool {
+        let Some(command) = self.current(tracks_asset).undo.pop_back() else {
+            return false;
+        };
+        self.apply(&command.inverse(), control_points, tracks_asset);
+        self.current(tracks_asset).redo.push(command);
+        true
+    }
+
+    /// Applies the last undone command on the current track again. Returns `false` if there was
+    /// nothing to redo.
+    pub fn redo(&mut self, control_points: &mut ControlPoints, tracks_asset: &mut TracksAsset) -> bool {
+        let Some(command) = self.current(tracks_asset).redo.pop() else {
+            return false;
+        };
+        self.apply(&command, control_points, tracks_asset);
+        self.current(tracks_asset).push_undo(command);
+        true
+    }
+
+    /// Forgets everything, for when another file is opened.
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Applies `command`, keeping each history with its track when tracks are added or removed.
+    fn apply(
+        &mut self,
+        command: &EditCommand,
+        control_points: &mut ControlPoints,
+        tracks_asset: &mut TracksAsset,
+    ) {
+        match command {
+            EditCommand::AddTrack { index, .. } => {
+                self.move_tracks(|track| if track >= *index { Some(track + 1) } else { Some(track) });
+            }
+            EditCommand::RemoveTrack { index, .. } => {
+                self.move_tracks(|track| match track.cmp(index) {
+                    Ordering::Less => Some(track),
+                    Ordering::Equal => None,
+                    Ordering::Greater => Some(track - 1),
+                });
+            }
+            EditCommand::Group(commands) => {
+                for command in commands {
+                    self.apply(command, control_points, tracks_asset);
+                }
+                return;
+            }
+            _ => {}
+        }
+        command.apply(control_points, tracks_asset);
+    }
+
+    /// Gives the history of each track to the index `moved` returns for it, or forgets it for
+    /// `None`.
+    fn move_tracks(&mut self, moved: impl Fn(usize) -> Option<usize>) {
+        self.tracks = self
+            .tracks
+            .drain()
+            .filter_map(|(track, history)| Some((moved(track)?, history)))
+            .collect();
+    }
+
+    fn current(&mut self, tracks_asset: &TracksAsset) -> &mut TrackHistory {
+        let index = tracks_asset.current_track_index.unwrap_or_default();
+        self.tracks.entry(index).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::racing::corners::CornerName;
+    use crate::racing::props::{PropKind, TrackProp};
+    use crate::racing::surface::{SurfaceKind, SurfaceZone, TrackSide};
+
+    fn editing(track: RaceTrack) -> (ControlPoints, TracksAsset) {
+        let control_points = ControlPoints {
+            points: track.points.clone(),
+            widths: track.point_widths(),
+            selected: None,
+        };
+        let tracks_asset = TracksAsset {
+            tracks: vec![track],
+            current_track_index: Some(0),
+            ..Default::default()
+        };
+        (control_points, tracks_asset)
+    }
+
+    fn open_track() -> RaceTrack {
+        let points = vec![
+            vec2(0.0, 0.0),
+            vec2(300.0, 0.0),
+            vec2(500.0, 150.0),
+            vec2(500.0, 450.0),
+            vec2(300.0, 600.0),
+        ];
+        RaceTrack {
+            track_name: "Sprint".to_string(),
+            widths: vec![40.0, 50.0, 60.0, 50.0, 40.0],
+            points,
+            closed: false,
+            start_line: 0.5,
+            finish_line: 3.5,
+            checkpoints: vec![1.5, 2.75],
+            surfaces: vec![SurfaceZone {
+                kind: SurfaceKind::Gravel,
+                start: 350.0,
+                end: 500.0,
+                side: Some(TrackSide::Left),
+            }],
+            props: vec![TrackProp {
+                kind: PropKind::Barrel,
+                distance: 420.0,
+                lateral_offset: 30.0,
+                rotation: 0.0,
+            }],
+            corner_names: vec![CornerName {
+                distance: 450.0,
+                name: "Hairpin".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Whether `a` and `b` are the same apart from rounding in their numbers.
+    fn nearly_equal(a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => {
+                (a.as_f64().unwrap() - b.as_f64().unwrap()).abs() < 1e-3
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| nearly_equal(a, b))
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| nearly_equal(a, b)))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    #[test]
+    fn undoing_an_insert_restores_the_whole_track() {
+        let original = open_track();
+        let (mut control_points, mut tracks_asset) = editing(original.clone());
+        let mut history = EditHistory::default();
+
+        history.perform(
+            EditCommand::InsertPoint {
+                index: 2,
+                point: vec2(420.0, 60.0),
+                width: 55.0,
+            },
+            &mut control_points,
+            &mut tracks_asset,
+        );
+        assert_eq!(tracks_asset.tracks[0].points.len(), original.points.len() + 1);
+        assert!(history.undo(&mut control_points, &mut tracks_asset));
+
+        let restored = serde_json::to_value(&tracks_asset.tracks[0]).unwrap();
+        let original = serde_json::to_value(&original).unwrap();
+        assert!(nearly_equal(&restored, &original), "{restored:#} != {original:#}");
+    }
+
+    #[test]
+    fn undoing_an_added_track_removes_it_from_the_file() {
+        let (mut control_points, mut tracks_asset) = editing(open_track());
+        let mut history = EditHistory::default();
+        let added = RaceTrack {
+            track_name: "Added".to_string(),
+            ..Default::default()
+        };
+
+        history.perform(
+            EditCommand::AddTrack {
+                index: 1,
+                track: Box::new(added.clone()),
+                from: 0,
+            },
+            &mut control_points,
+            &mut tracks_asset,
+        );
+        assert_eq!(tracks_asset.tracks.len(), 2);
+        assert_eq!(tracks_asset.current_track_index, Some(1));
+
+        assert!(history.undo(&mut control_points, &mut tracks_asset));
+        assert_eq!(tracks_asset.tracks, vec![open_track()]);
+        assert_eq!(tracks_asset.current_track_index, Some(0));
+        assert_eq!(control_points.points, open_track().points);
+
+        assert!(history.redo(&mut control_points, &mut tracks_asset));
+        assert_eq!(tracks_asset.tracks, vec![open_track(), added]);
+        assert_eq!(tracks_asset.current_track_index, Some(1));
+    }
+
+    #[test]
+    fn histories_stay_with_their_tracks_when_a_track_is_added_before_them() {
+        let (mut control_points, mut tracks_asset) = editing(open_track());
+        let mut history = EditHistory::default();
+        history.perform(
+            EditCommand::RenameTrack {
+                from: "Sprint".to_string(),
+                to: "Renamed".to_string(),
+            },
+            &mut control_points,
+            &mut tracks_asset,
+        );
+
+        history.perform(
+            EditCommand::AddTrack {
+                index: 0,
+                track: Box::default(),
+                from: 0,
+            },
+            &mut control_points,
+            &mut tracks_asset,
+        );
+        history.perform(
+            EditCommand::SwitchTrack { from: 0, to: 1 },
+            &mut control_points,
+            &mut tracks_asset,
+        );
+        // Takes back the switch, which belongs to the track switched to.
+        assert!(history.undo(&mut control_points, &mut tracks_asset));
+        assert!(history.undo(&mut control_points, &mut tracks_asset));
+        assert_eq!(tracks_asset.tracks.len(), 1);
+        assert_eq!(tracks_asset.tracks[0].track_name, "Renamed");
+
+        assert!(history.undo(&mut control_points, &mut tracks_asset));
+        assert_eq!(tracks_asset.tracks[0].track_name, "Sprint");
+    }
+}
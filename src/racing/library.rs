@@ -0,0 +1,343 @@
+//! The track library: every track the game knows about, gathered from the `.tracks` files in the
+//! bundled tracks folder and in the player's own tracks folder.
+//!
+//! Tracks are spread over many small files, so people can each work on their own without
+//! fighting over a single one. The library reads and writes those files directly rather than
+//! going through the asset server, because the user folder lives outside the assets folder and
+//! the editor has to write back to it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use bevy::log::warn;
+use bevy::prelude::Resource;
+use thiserror::Error;
+
+use super::format::{self, FormatError};
+use super::{RaceTrack, TracksAsset};
+
+/// The folder holding the tracks that ship with the game.
+pub const BUNDLED_TRACKS_DIR: &str = "assets/tracks";
+
+/// The extension of files holding tracks.
+pub const TRACKS_EXTENSION: &str = "tracks";
+
+/// The file in the user folder that new tracks are saved to when no other file is chosen.
+pub const DEFAULT_USER_FILE: &str = "my.tracks";
+
+/// The folder the game keeps its files in, inside the platform's data folder.
+const APP_DIR: &str = "bevy-jam-six";
+
+/// Where a tracks file was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackSource {
+    /// Shipped with the game, in [`BUNDLED_TRACKS_DIR`].
+    Bundled,
+    /// Made by the player, in [`user_tracks_dir`].
+    User,
+}
+
+impl TrackSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            TrackSource::Bundled => "bundled",
+            TrackSource::User => "user",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LibraryError {
+    #[error("Could not read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("Could not read {}: {source}", path.display())]
+    Format { path: PathBuf, source: FormatError },
+    #[error("Could not write {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+    #[error("Could not write {}: {source}", path.display())]
+    Serialize {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// Saving over a file the library could not read would lose whatever was in it.
+    #[error("Not saving over {}, which could not be read", path.display())]
+    Unreadable { path: PathBuf },
+}
+
+impl LibraryError {
+    /// The file or folder the error is about.
+    pub fn path(&self) -> &Path {
+        match self {
+            LibraryError::Read { path, .. }
+            | LibraryError::Format { path, .. }
+            | LibraryError::Write { path, .. }
+            | LibraryError::Serialize { path, .. }
+            | LibraryError::Unreadable { path } => path,
+        }
+    }
+}
+
+/// One `.tracks` file in the library.
+#[derive(Debug, Clone)]
+pub struct TrackFile {
+    pub path: PathBuf,
+    pub source: TrackSource,
+    pub tracks: TracksAsset,
+}
+
+/// A track in the library, together with the file it came from.
+#[derive(Debug, Clone, Copy)]
+pub struct CatalogueEntry<'a> {
+    pub track: &'a RaceTrack,
+    pub file: &'a TrackFile,
+    /// The position of the file in [`TrackLibrary::files`].
+    pub file_index: usize,
+    /// The position of the track in its file.
+    pub track_index: usize,
+}
+
+/// All tracks files that could be found, bundled ones first.
+#[derive(Resource, Debug, Default)]
+pub struct TrackLibrary {
+    pub files: Vec<TrackFile>,
+    /// Files that were found but could not be read. They are left out of the library.
+    pub errors: Vec<LibraryError>,
+}
+
+impl TrackLibrary {
+    /// Scans the bundled tracks folder and, if there is one, the user tracks folder.
+    pub fn scan() -> Self {
+        let mut folders = vec![(PathBuf::from(BUNDLED_TRACKS_DIR), TrackSource::Bundled)];
+        folders.extend(user_tracks_dir().map(|dir| (dir, TrackSource::User)));
+        Self::scan_folders(&folders)
+    }
+
+    /// Reads every tracks file directly inside each of `folders`. Missing folders are skipped.
+    pub fn scan_folders(folders: &[(PathBuf, TrackSource)]) -> Self {
+        let mut library = Self::default();
+        for (folder, source) in folders {
+            let paths = match track_files_in(folder) {
+                Ok(paths) => paths,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    library.errors.push(LibraryError::Read {
+                        path: folder.clone(),
+                        source: err,
+                    });
+                    continue;
+                }
+            };
+            for path in paths {
+                match load_file(&path) {
+                    Ok(tracks) => library.files.push(TrackFile {
+                        path,
+                        source: *source,
+                        tracks,
+                    }),
+                    Err(err) => library.errors.push(err),
+                }
+            }
+        }
+        library
+    }
+
+    /// Every track in every file, in file order.
+    pub fn catalogue(&self) -> impl Iterator<Item = CatalogueEntry<'_>> {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(file_index, file)| {
+                file.tracks
+                    .tracks
+                    .iter()
+                    .enumerate()
+                    .map(move |(track_index, track)| CatalogueEntry {
+                        track,
+                        file,
+                        file_index,
+                        track_index,
+                    })
+            })
+    }
+
+    /// The track called `track_name`. When several files have a track by that name, the one found
+    /// last wins, so a user track overrides a bundled one.
+    pub fn find(&self, track_name: &str) -> Option<CatalogueEntry<'_>> {
+        self.catalogue()
+            .filter(|entry| entry.track.track_name == track_name)
+            .last()
+    }
+
+    /// The position of the file at `path` in [`TrackLibrary::files`].
+    pub fn file_index(&self, path: &Path) -> Option<usize> {
+        self.files.iter().position(|file| file.path == path)
+    }
+
+    /// The files a track can be saved to: every file in the library, and the default file in the
+    /// user folder even if it does not exist yet.
+    pub fn save_targets(&self) -> Vec<PathBuf> {
+        let mut targets = self
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+        let default_file = user_tracks_dir()
+            .map(|dir| dir.join(DEFAULT_USER_FILE))
+            .filter(|default_file| !targets.contains(default_file));
+        targets.extend(default_file);
+        targets
+    }
+
+    /// Writes `tracks` to `path` and puts it in the library in place of whatever was there.
+    /// Refuses to write over a file that was found but could not be read.
+    pub fn save_file(&mut self, path: &Path, tracks: &mut TracksAsset) -> Result<(), LibraryError> {
+        self.check_not_unreadable(path)?;
+        write_file(path, tracks)?;
+        match self.file_index(path) {
+            Some(index) => self.files[index].tracks = tracks.clone(),
+            None => self.files.push(TrackFile {
+                path: path.to_path_buf(),
+                source: source_of(path),
+                tracks: tracks.clone(),
+            }),
+        }
+        Ok(())
+    }
+
+    /// Saves `track` into the file at `path`, replacing the track of the same name if the file
+    /// has one and adding it otherwise. The file is created if it does not exist.
+    pub fn save_track(&mut self, path: &Path, track: &RaceTrack) -> Result<(), LibraryError> {
+        self.check_not_unreadable(path)?;
+        let mut tracks = match self.file_index(path) {
+            Some(index) => self.files[index].tracks.clone(),
+            None if path.exists() => load_file(path)?,
+            None => TracksAsset {
+                tracks: Vec::new(),
+                current_track_index: None,
+                ..Default::default()
+            },
+        };
+        match tracks
+            .tracks
+            .iter()
+            .position(|existing| existing.track_name == track.track_name)
+        {
+            Some(index) => {
+                tracks.tracks[index] = track.clone();
+                tracks.current_track_index = Some(index);
+            }
+            None => tracks.store_track(track.clone()),
+        }
+        self.save_file(path, &mut tracks)
+    }
+
+    /// Fails for a file that was found but could not be read.
+    fn check_not_unreadable(&self, path: &Path) -> Result<(), LibraryError> {
+        if self.errors.iter().any(|err| err.path() == path) {
+            return Err(LibraryError::Unreadable {
+                path: path.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The folder the player's own tracks are kept in, if the platform has a place for them.
+pub fn user_tracks_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(APP_DIR).join("tracks"))
+}
+
+#[cfg(target_os = "windows")]
+fn data_dir() -> Option<PathBuf> {
+    env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn data_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn data_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+}
+
+/// Whether the file at `path` is one of the player's own.
+fn source_of(path: &Path) -> TrackSource {
+    match user_tracks_dir() {
+        Some(dir) if path.starts_with(&dir) => TrackSource::User,
+        _ => TrackSource::Bundled,
+    }
+}
+
+/// The tracks files directly inside `folder`, sorted by name.
+fn track_files_in(folder: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == TRACKS_EXTENSION) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Reads the tracks file at `path`, upgrading it to the current format.
+pub fn load_file(path: &Path) -> Result<TracksAsset, LibraryError> {
+    let bytes = fs::read(path).map_err(|source| LibraryError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let tracks = format::from_slice(&bytes).map_err(|source| LibraryError::Format {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    for broken in &tracks.broken_tracks {
+        warn!(
+            "Skipping broken {broken} in {}, it will be saved back unchanged",
+            path.display()
+        );
+    }
+    Ok(tracks)
+}
+
+/// Writes `tracks` to `path`, creating its folder if needed.
+pub fn write_file(path: &Path, tracks: &mut TracksAsset) -> Result<(), LibraryError> {
+    let json = format::to_string_pretty(tracks).map_err(|source| LibraryError::Serialize {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let write = |source| LibraryError::Write {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).map_err(write)?;
+    }
+    fs::write(path, json).map_err(write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_that_could_not_be_read_are_not_saved_over() {
+        let folder = env::temp_dir().join(format!("{APP_DIR}-library-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join(DEFAULT_USER_FILE);
+        fs::write(&path, "{ not json").unwrap();
+
+        let mut library = TrackLibrary::scan_folders(&[(folder.clone(), TrackSource::User)]);
+        let saved = library.save_track(&path, &RaceTrack::default());
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert!(matches!(saved, Err(LibraryError::Unreadable { .. })), "{saved:?}");
+        assert_eq!(contents, "{ not json");
+    }
+}
@@ -0,0 +1,464 @@
+//! Building the road mesh of a [`RaceTrack`].
+//!
+//! The editor and gameplay both sample the edges with [`RaceTrack::edges`] and draw the road from
+//! them with [`build_track_mesh`], which produces one indexed mesh for the whole track, reusing the
+//! same edges for the bridges. [`RaceTrack::build_mesh`] does both steps for a track without
+//! bridges to draw. The edges are offset along mitered normals so the road keeps
+//! its width through corners, and the inner edge is pulled in on corners tighter than the road is
+//! wide so it never folds over itself. Surface zones are coloured in with vertex colours, and the
+//! ends of open tracks are rounded off with caps. Bridges get a mesh of their own from
+//! [`build_bridge_mesh`], so they can be drawn above the road they cross.
+
+use std::f32::consts::PI;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::color::{Color, ColorToComponents, LinearRgba};
+use bevy::math::Vec2;
+use bevy::render::mesh::{Indices, Mesh, PrimitiveTopology};
+
+use super::arc_length::TRACK_SAMPLE_SPACING;
+use super::bridge::Bridge;
+use super::surface::{SurfaceKind, SurfaceZone, TrackSide, surface_at};
+use super::{MIN_TRACK_WIDTH, RaceTrack};
+
+/// How many world units along the track one repeat of the road texture covers.
+pub const DEFAULT_TEXTURE_LENGTH: f32 = 100.0;
+
+/// The cosine of the sharpest angle the miter is allowed to make with the segment normals, which
+/// limits the outer edge of a kink to twice the half width.
+const MIN_MITER_COS: f32 = 0.5;
+
+/// The inner edge of a corner stays within this fraction of the corner radius from the centerline.
+const INNER_EDGE_LIMIT: f32 = 0.9;
+
+/// How many straight pieces the rounded cap at each end of an open track is made of.
+pub const CAP_SEGMENTS: usize = 8;
+
+/// The edges of the road, sampled evenly along the centerline.
+#[derive(Debug, Clone, Default)]
+pub struct TrackEdges {
+    /// Whether the last sample joins up with the first. Open tracks have a sample at each end.
+    pub closed: bool,
+    /// The distance along the centerline of every sample.
+    pub distances: Vec<f32>,
+    pub centers: Vec<Vec2>,
+    /// The left edge of the road, seen in the direction of travel.
+    pub left: Vec<Vec2>,
+    /// The right edge of the road, seen in the direction of travel.
+    pub right: Vec<Vec2>,
+    /// The total length of the centerline.
+    pub length: f32,
+}
+
+/// The left edge, centre and right edge of the road at one distance along the centerline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeRow {
+    pub left: Vec2,
+    pub center: Vec2,
+    pub right: Vec2,
+}
+
+impl TrackEdges {
+    pub fn len(&self) -> usize {
+        self.centers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.centers.is_empty()
+    }
+
+    /// The distance along the centerline between two samples.
+    pub fn spacing(&self) -> f32 {
+        let gaps = if self.closed {
+            self.len()
+        } else {
+            self.len().saturating_sub(1)
+        };
+        self.length / gaps.max(1) as f32
+    }
+
+    /// Wraps a distance around a closed track, or clamps it to the ends of an open one.
+    pub fn wrap_distance(&self, distance: f32) -> f32 {
+        if self.length <= 0.0 {
+            0.0
+        } else if self.closed {
+            distance.rem_euclid(self.length)
+        } else {
+            distance.clamp(0.0, self.length)
+        }
+    }
+
+    /// The row at `distance` along the centerline, interpolated between the samples around it.
+    pub fn row_at(&self, distance: f32) -> EdgeRow {
+        let count = self.len();
+        if count == 0 || self.length <= 0.0 {
+            return EdgeRow {
+                left: Vec2::ZERO,
+                center: Vec2::ZERO,
+                right: Vec2::ZERO,
+            };
+        }
+        let scaled = self.wrap_distance(distance) / self.spacing();
+        let last_index = if self.closed {
+            count - 1
+        } else {
+            count.saturating_sub(2)
+        };
+        let index = (scaled.floor() as usize).min(last_index);
+        let next = (index + 1) % count;
+        let fraction = (scaled - index as f32).clamp(0.0, 1.0);
+        EdgeRow {
+            left: self.left[index].lerp(self.left[next], fraction),
+            center: self.centers[index].lerp(self.centers[next], fraction),
+            right: self.right[index].lerp(self.right[next], fraction),
+        }
+    }
+
+    /// The rows from `start` to `end` along the centerline: the exact ends and every sample in
+    /// between. On a closed track an `end` before `start` runs across the start of the curve, on an
+    /// open one the two are swapped.
+    pub fn span(&self, start: f32, end: f32) -> Vec<EdgeRow> {
+        if self.is_empty() || self.length <= 0.0 {
+            return Vec::new();
+        }
+        let mut start = self.wrap_distance(start);
+        let mut end = self.wrap_distance(end);
+        if end <= start {
+            if self.closed {
+                end += self.length;
+            } else {
+                (start, end) = (end, start);
+            }
+        }
+
+        let spacing = self.spacing();
+        let first_sample = (start / spacing).floor() as usize + 1;
+        let last_sample = (end / spacing).ceil() as usize;
+        let mut rows = vec![self.row_at(start)];
+        rows.extend((first_sample..last_sample).map(|i| self.row_at(i as f32 * spacing)));
+        rows.push(self.row_at(end));
+        rows
+    }
+
+    /// The rounded cap behind the start of an open track, from the right edge around to the left.
+    pub fn start_cap(&self) -> Option<Vec<Vec2>> {
+        let (left, right) = (*self.left.first()?, *self.right.first()?);
+        (!self.closed).then(|| cap_arc(right, left))
+    }
+
+    /// The rounded cap beyond the end of an open track, from the left edge around to the right.
+    pub fn end_cap(&self) -> Option<Vec<Vec2>> {
+        let (left, right) = (*self.left.last()?, *self.right.last()?);
+        (!self.closed).then(|| cap_arc(left, right))
+    }
+}
+
+/// A half circle from `from` to `to`, turning clockwise around the point between them.
+fn cap_arc(from: Vec2, to: Vec2) -> Vec<Vec2> {
+    let center = from.midpoint(to);
+    let radius = from - center;
+    (0..=CAP_SEGMENTS)
+        .map(|i| center + Vec2::from_angle(-PI * i as f32 / CAP_SEGMENTS as f32).rotate(radius))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackMeshSettings {
+    /// The distance between samples along the centerline, in world units.
+    pub spacing: f32,
+    /// How many world units along the track one repeat of the texture covers.
+    pub texture_length: f32,
+    /// The colour of the plain road, white if not set. Surface zones get their own colour, and
+    /// the material colour is multiplied with both.
+    pub vertex_color: Option<Color>,
+}
+
+impl Default for TrackMeshSettings {
+    fn default() -> Self {
+        Self {
+            spacing: TRACK_SAMPLE_SPACING,
+            texture_length: DEFAULT_TEXTURE_LENGTH,
+            vertex_color: None,
+        }
+    }
+}
+
+impl RaceTrack {
+    /// The edges of the road sampled every `spacing` world units, or `None` if the track has too
+    /// few points for a curve.
+    pub fn edges(&self, spacing: f32) -> Option<TrackEdges> {
+        let table = self.arc_length_table()?;
+        let width_curve = self.form_width_curve()?;
+        let params = table.uniform_params(spacing);
+        let centers = params
+            .iter()
+            .map(|&t| table.curve().position(t))
+            .collect::<Vec<_>>();
+
+        let count = centers.len();
+        let mut edges = TrackEdges {
+            closed: self.closed,
+            distances: params.iter().map(|&t| table.distance_at_t(t)).collect(),
+            length: table.length(),
+            ..Default::default()
+        };
+        for (i, &t) in params.iter().enumerate() {
+            let center = centers[i];
+            let mut previous = centers[(i + count - 1) % count];
+            let mut next = centers[(i + 1) % count];
+            // The ends of an open track carry straight on.
+            if !self.closed && i == 0 {
+                previous = center * 2.0 - next;
+            }
+            if !self.closed && i == count - 1 {
+                next = center * 2.0 - previous;
+            }
+
+            // Offsetting along the average of the two segment normals keeps the road the same
+            // width on both sides of a sample.
+            let incoming = (center - previous).normalize_or_zero().perp();
+            let outgoing = (next - center).normalize_or_zero().perp();
+            let miter = (incoming + outgoing).normalize_or(outgoing);
+            let half_width = width_curve.position(t).max(MIN_TRACK_WIDTH) / 2.0;
+            let miter_length = half_width / miter.dot(outgoing).max(MIN_MITER_COS);
+
+            let curvature = table.curvature_at_t(t);
+            let inner_length = miter_length.min(INNER_EDGE_LIMIT / curvature.abs());
+            let (left_length, right_length) = if curvature > 0.0 {
+                (inner_length, miter_length)
+            } else {
+                (miter_length, inner_length)
+            };
+
+            edges.centers.push(center);
+            edges.left.push(center + miter * left_length);
+            edges.right.push(center - miter * right_length);
+        }
+        Some(edges)
+    }
+
+    /// Builds the road as a single mesh, or `None` if the track has too few points for a curve.
+    pub fn build_mesh(&self, settings: &TrackMeshSettings) -> Option<Mesh> {
+        self.edges(settings.spacing)
+            .map(|edges| build_track_mesh(&edges, &self.surfaces, settings))
+    }
+}
+
+/// Builds a strip of triangles between the edges of the road, coloured by `surfaces`.
+///
+/// Every row of the strip has a vertex on each edge and two on the centerline, one for each half
+/// of the road, so a zone on one side does not bleed into the other. Rows are added at the ends of
+/// every zone, doubled up with the colours on either side, so colours change sharply where the
+/// zones do instead of blending over a sample.
+///
+/// The texture runs across the road in `u` and along it in `v`. On a closed track the first row is
+/// repeated at the end of the strip to let `v` run all the way to the length of the track instead
+/// of wrapping back to zero. Open tracks get a rounded cap at either end instead.
+pub fn build_track_mesh(
+    edges: &TrackEdges,
+    surfaces: &[SurfaceZone],
+    settings: &TrackMeshSettings,
+) -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    if !edges.is_empty() && edges.length > 0.0 {
+        let base_color = settings.vertex_color.unwrap_or(Color::WHITE);
+        let color_at = |distance: f32, side: TrackSide| {
+            surface_at(surfaces, distance, side, edges.length).map_or(base_color, SurfaceKind::color)
+        };
+
+        let mut distances = edges.distances.clone();
+        distances.extend(
+            surfaces
+                .iter()
+                .flat_map(|zone| [zone.start, zone.end])
+                .map(|distance| edges.wrap_distance(distance)),
+        );
+        if edges.closed {
+            distances.push(edges.length);
+        }
+        distances.sort_by(f32::total_cmp);
+        distances.dedup_by(|a, b| (*a - *b).abs() <= f32::EPSILON * edges.length);
+
+        // The colours of the left and right half of the road between two rows.
+        let spans = distances
+            .windows(2)
+            .map(|pair| {
+                let middle = (pair[0] + pair[1]) / 2.0;
+                [color_at(middle, TrackSide::Left), color_at(middle, TrackSide::Right)]
+            })
+            .collect::<Vec<_>>();
+
+        let texture_length = settings.texture_length.max(f32::EPSILON);
+        let mut row_count = 0u32;
+        for (i, &distance) in distances.iter().enumerate() {
+            let before = i.checked_sub(1).map(|span| spans[span]);
+            let after = spans.get(i).copied();
+            let row_colors = match (before, after) {
+                (Some(before), Some(after)) if before != after => vec![before, after],
+                (Some(colors), _) | (None, Some(colors)) => vec![colors],
+                (None, None) => Vec::new(),
+            };
+
+            let row = edges.row_at(distance);
+            let v = distance / texture_length;
+            for [left_color, right_color] in row_colors {
+                if row_count > 0 {
+                    let (previous, current) = ((row_count - 1) * 4, row_count * 4);
+                    // The left half between the left edge and the centre, then the right half.
+                    for half in [0, 2] {
+                        let (a0, a1) = (previous + half, previous + half + 1);
+                        let (b0, b1) = (current + half, current + half + 1);
+                        indices.extend([a0, a1, b0, a1, b1, b0]);
+                    }
+                }
+                for (position, u) in [
+                    (row.left, 0.0),
+                    (row.center, 0.5),
+                    (row.center, 0.5),
+                    (row.right, 1.0),
+                ] {
+                    positions.push([position.x, position.y, 0.0]);
+                    uvs.push([u, v]);
+                }
+                let left_color = LinearRgba::from(left_color).to_f32_array();
+                let right_color = LinearRgba::from(right_color).to_f32_array();
+                colors.extend([left_color, left_color, right_color, right_color]);
+                row_count += 1;
+            }
+        }
+
+        let cap_color = LinearRgba::from(base_color).to_f32_array();
+        for (cap, distance) in [(edges.start_cap(), 0.0), (edges.end_cap(), edges.length)] {
+            let Some(cap) = cap else {
+                continue;
+            };
+            // A fan of triangles around the middle of the end of the road.
+            let center = cap[0].midpoint(cap[cap.len() - 1]);
+            let first = positions.len() as u32;
+            let v = distance / texture_length;
+            for position in std::iter::once(center).chain(cap.iter().copied()) {
+                positions.push([position.x, position.y, 0.0]);
+                uvs.push([0.5, v]);
+                colors.push(cap_color);
+            }
+            for k in 1..cap.len() as u32 {
+                indices.extend([first, first + k, first + k + 1]);
+            }
+        }
+    }
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// Builds the road of a bridge, to be drawn above the rest of the track at
+/// [`BRIDGE_Z`](super::bridge::BRIDGE_Z). Surface zones are not drawn on bridges.
+pub fn build_bridge_mesh(edges: &TrackEdges, bridge: &Bridge, settings: &TrackMeshSettings) -> Mesh {
+    let rows = edges.span(bridge.start, bridge.end);
+    let color = LinearRgba::from(settings.vertex_color.unwrap_or(Color::WHITE)).to_f32_array();
+    let texture_length = settings.texture_length.max(f32::EPSILON);
+
+    let mut positions = Vec::with_capacity(rows.len() * 2);
+    let mut uvs = Vec::with_capacity(rows.len() * 2);
+    let mut distance = edges.wrap_distance(bridge.start);
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            distance += row.center.distance(rows[i - 1].center);
+        }
+        let v = distance / texture_length;
+        positions.push([row.left.x, row.left.y, 0.0]);
+        positions.push([row.right.x, row.right.y, 0.0]);
+        uvs.push([0.0, v]);
+        uvs.push([1.0, v]);
+    }
+    let indices = (0..rows.len().saturating_sub(1) as u32)
+        .flat_map(|i| {
+            let (left, right) = (i * 2, i * 2 + 1);
+            [left, right, left + 2, right, right + 2, left + 2]
+        })
+        .collect::<Vec<_>>();
+
+    let vertex_count = positions.len();
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![color; vertex_count]);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    fn positions(mesh: &Mesh) -> Vec<Vec2> {
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|positions| positions.as_float3())
+            .unwrap()
+            .iter()
+            .map(|[x, y, _]| vec2(*x, *y))
+            .collect()
+    }
+
+    #[test]
+    fn only_open_tracks_get_end_caps() {
+        let mut track = RaceTrack {
+            points: vec![vec2(0.0, 0.0), vec2(200.0, 0.0), vec2(400.0, 0.0)],
+            widths: vec![40.0; 3],
+            closed: false,
+            ..Default::default()
+        };
+        let open = positions(&track.build_mesh(&TrackMeshSettings::default()).unwrap());
+        // The caps round off the road half its width beyond either end.
+        let min_x = open.iter().map(|position| position.x).fold(f32::INFINITY, f32::min);
+        let max_x = open.iter().map(|position| position.x).fold(f32::NEG_INFINITY, f32::max);
+        assert!((min_x + 20.0).abs() < 0.5, "{min_x}");
+        assert!((max_x - 420.0).abs() < 0.5, "{max_x}");
+
+        track.closed = true;
+        let edges = track.edges(TRACK_SAMPLE_SPACING).unwrap();
+        assert!(edges.start_cap().is_none() && edges.end_cap().is_none());
+    }
+
+    #[test]
+    fn the_inner_edge_does_not_fold_in_a_tight_corner() {
+        // A hairpin with a radius of 20 on a road 60 wide, with control points evenly spaced so
+        // the centerline itself stays round.
+        let straight = |y: f32| (0..5).map(move |i| vec2(140.0 + i as f32 * 15.0, y));
+        let mut points = straight(-20.0).collect::<Vec<_>>();
+        points.extend((1..4).map(|i| {
+            let angle = -std::f32::consts::FRAC_PI_2 + i as f32 * std::f32::consts::FRAC_PI_4;
+            vec2(200.0, 0.0) + Vec2::from_angle(angle) * 20.0
+        }));
+        points.extend(straight(20.0).rev());
+        let track = RaceTrack {
+            widths: vec![60.0; points.len()],
+            points,
+            closed: false,
+            ..Default::default()
+        };
+        let edges = track.edges(TRACK_SAMPLE_SPACING).unwrap();
+        // The hairpin turns left, so the left edge is on the inside.
+        for i in 1..edges.len() {
+            let forward = edges.centers[i] - edges.centers[i - 1];
+            let step = edges.left[i] - edges.left[i - 1];
+            assert!(step.dot(forward) >= 0.0, "the inner edge runs backwards at sample {i}");
+        }
+    }
+}
@@ -1,17 +1,42 @@
 use bevy::asset::io::Reader;
-use bevy::asset::{AssetLoader, AssetServer, Handle, LoadContext};
-use bevy::audio::AudioSource;
-use bevy::image::{ImageLoaderSettings, ImageSampler};
-use bevy::math::{Vec2, vec2};
-use bevy::platform::collections::HashMap;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::math::{Vec2, VectorSpace, vec2};
+use bevy::log::warn;
 use bevy::prelude::{
-    Asset, Component, CubicCardinalSpline, CubicCurve, CyclicCubicGenerator, FromWorld, Reflect,
-    Resource, World,
+    Asset, Component, CubicCardinalSpline, CubicCurve, CubicGenerator, CyclicCubicGenerator,
+    Reflect, Resource,
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-pub const RESOLUTION: usize = 5;
+pub mod arc_length;
+pub mod bridge;
+pub mod corners;
+pub mod cup;
+pub mod format;
+pub mod gates;
+pub mod generator;
+pub mod history;
+pub mod library;
+pub mod mesh;
+pub mod physics;
+pub mod points;
+pub mod progress;
+pub mod props;
+pub mod racing_line;
+pub mod share;
+pub mod stats;
+pub mod surface;
+pub mod validation;
+
+/// The full width of the road at a control point when nothing else has been set.
+pub const DEFAULT_TRACK_WIDTH: f32 = 40.0;
+
+/// Interpolated widths are clamped to this, so the road never pinches to nothing.
+pub const MIN_TRACK_WIDTH: f32 = 8.0;
+
+/// How close to a control point the cursor has to be to pick it in the editor.
+pub const POINT_PICK_RADIUS: f32 = 15.0;
 
 #[derive(Component)]
 pub struct TrackPart;
@@ -26,54 +51,111 @@ pub struct Curves(pub Option<CubicCurve<Vec2>>);
 #[derive(Clone, Resource)]
 pub struct ControlPoints {
     pub points: Vec<Vec2>,
+    /// The full width of the road at each of the `points`.
+    pub widths: Vec<f32>,
     pub selected: Option<usize>,
 }
 
+impl ControlPoints {
+    /// The index of the control point closest to `position`, if any is within
+    /// [`POINT_PICK_RADIUS`].
+    pub fn point_near(&self, position: Vec2) -> Option<usize> {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| (index, point.distance(position)))
+            .filter(|(_, distance)| *distance <= POINT_PICK_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
+
 #[derive(Debug, Clone, Resource, Default)]
 pub struct CurrentTrack(pub Option<RaceTrack>);
 
-#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Reflect)]
 pub struct RaceTrack {
     pub track_name: String,
     pub points: Vec<Vec2>,
+    /// The full width of the road at each of the `points`, interpolated along the curve.
+    pub widths: Vec<f32>,
+    /// Whether the track is a loop. Open tracks run from the first control point to the last, see
+    /// [`gates::RaceMode`].
+    pub closed: bool,
+    /// The curve parameter of the start line, which is also the finish line on closed tracks, see
+    /// [`gates`].
+    pub start_line: f32,
+    /// The curve parameter of the finish line on open tracks.
+    pub finish_line: f32,
+    /// The curve parameters of the checkpoint gates, in the order they have to be passed.
+    pub checkpoints: Vec<f32>,
+    /// The number of slots on the starting grid.
+    pub grid_size: usize,
+    /// Stretches of gravel, ice and the like, see [`surface`].
+    pub surfaces: Vec<surface::SurfaceZone>,
+    /// Stretches of the track on the upper level, see [`bridge`].
+    pub bridges: Vec<bridge::Bridge>,
+    /// Tyre stacks, barrels and the like placed along the track, see [`props`].
+    pub props: Vec<props::TrackProp>,
+    /// Names given to corners, which are otherwise called by their number, see [`corners`].
+    pub corner_names: Vec<corners::CornerName>,
 }
 
 impl RaceTrack {
     pub fn form_curve(&self) -> Curves {
-        let points = self.points.iter().copied();
-        let spline = CubicCardinalSpline::new_catmull_rom(points);
-
-        Curves(spline.to_curve_cyclic().ok())
-    }
-
-    pub fn get_bounds(&self) -> Vec<(Vec2, Vec2)> {
-        let mut normals = Vec::new();
-        let tension = 0.5;
-        let binding = self.form_curve();
-        let track_curve = binding.0.as_ref().unwrap();
-        let resolution = RESOLUTION * track_curve.segments().len();
-        let track_curve = track_curve.iter_positions(resolution).collect::<Vec<_>>();
-
-        for i in 0..track_curve.len() {
-            let tangent = if i == 0 {
-                // Forward difference at start
-                (track_curve[i + 1] - track_curve[i]) * tension * 2.0
-            } else if i == track_curve.len() - 1 {
-                // Backward difference at end
-                (track_curve[i] - track_curve[i - 1]) * tension * 2.0
-            } else {
-                // Central difference for internal points
-                (track_curve[i + 1] - track_curve[i - 1]) * tension
-            };
-
-            let tangent = tangent.normalize_or_zero();
-
-            let normal = tangent.rotate(Vec2::from_angle(std::f32::consts::PI / -2.0)) * 20.0; // 90° rotation
-            let normal2 = normal.rotate(Vec2::from_angle(std::f32::consts::PI));
-
-            normals.push((track_curve[i] + normal, track_curve[i] + normal2));
+        Curves(self.form_spline(self.points.iter().copied()))
+    }
+
+    /// A Catmull-Rom curve through `values`, looping back to the first one if the track is
+    /// [`closed`](RaceTrack::closed).
+    fn form_spline<P: VectorSpace>(
+        &self,
+        values: impl IntoIterator<Item = P>,
+    ) -> Option<CubicCurve<P>> {
+        let spline = CubicCardinalSpline::new_catmull_rom(values);
+        if self.closed {
+            spline.to_curve_cyclic().ok()
+        } else {
+            spline.to_curve().ok()
+        }
+    }
+
+    /// The highest curve parameter on the track. Closed tracks wrap back to `t = 0` here.
+    pub fn max_t(&self) -> f32 {
+        if self.closed {
+            self.points.len() as f32
+        } else {
+            self.points.len().saturating_sub(1) as f32
         }
-        normals
+    }
+
+    /// The width set at a control point, falling back to [`DEFAULT_TRACK_WIDTH`] if the widths
+    /// are out of step with the points.
+    pub fn width_at_point(&self, index: usize) -> f32 {
+        self.widths
+            .get(index)
+            .copied()
+            .unwrap_or(DEFAULT_TRACK_WIDTH)
+    }
+
+    /// One width per control point, padded or truncated to match `points`.
+    pub fn point_widths(&self) -> Vec<f32> {
+        (0..self.points.len())
+            .map(|index| self.width_at_point(index))
+            .collect()
+    }
+
+    /// A curve over the widths with the same parameterization as [`RaceTrack::form_curve`], so
+    /// both can be sampled with the same `t`.
+    pub fn form_width_curve(&self) -> Option<CubicCurve<f32>> {
+        self.form_spline(self.point_widths())
+    }
+
+    /// The width of the road at curve parameter `t`.
+    pub fn width_at(&self, t: f32) -> f32 {
+        self.form_width_curve()
+            .map_or(DEFAULT_TRACK_WIDTH, |curve| curve.position(t))
+            .max(MIN_TRACK_WIDTH)
     }
 }
 
@@ -82,40 +164,65 @@ impl Default for RaceTrack {
         Self {
             track_name: String::new(),
             points: vec![vec2(-500., -200.), vec2(-500., -150.)],
+            widths: vec![DEFAULT_TRACK_WIDTH; 2],
+            closed: true,
+            start_line: 0.0,
+            finish_line: 0.0,
+            checkpoints: Vec::new(),
+            grid_size: gates::DEFAULT_GRID_SIZE,
+            surfaces: Vec::new(),
+            bridges: Vec::new(),
+            props: Vec::new(),
+            corner_names: Vec::new(),
         }
     }
 }
 
 #[derive(Debug, Clone, Resource, Asset, Reflect, Deserialize, Serialize)]
 pub struct TracksAsset {
+    /// The version of the `.tracks` format this asset was written with, see [`format`].
+    #[serde(default)]
+    pub format_version: u32,
+    #[serde(default)]
     pub tracks: Vec<RaceTrack>,
     pub current_track_index: Option<usize>,
+    /// Tracks in the file that could not be read. They are written back unchanged on save.
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub broken_tracks: Vec<format::BrokenTrack>,
 }
 
 impl Default for TracksAsset {
     fn default() -> Self {
         let mut asset = Self {
+            format_version: format::CURRENT_FORMAT_VERSION,
             tracks: Vec::new(),
             current_track_index: None,
+            broken_tracks: Vec::new(),
         };
-        let _ = asset.new_track();
+        asset.new_track();
         asset
     }
 }
 
 impl TracksAsset {
     pub fn new_track(&mut self) {
-        let name = format!("Track {}", self.tracks.len() + 1);
         let track = RaceTrack {
-            track_name: name,
-            points: vec![vec2(-500., -200.), vec2(-500., -150.)],
+            track_name: self.new_track_name(),
+            ..Default::default()
         };
         self.store_track(track);
     }
 
-    pub fn update_current_track(&mut self, points: Vec<Vec2>) {
-        if let Some(mut track) = self.get_current_track_mut() {
+    /// The name given to the next new track.
+    pub fn new_track_name(&self) -> String {
+        format!("Track {}", self.tracks.len() + 1)
+    }
+
+    pub fn update_current_track(&mut self, points: Vec<Vec2>, widths: Vec<f32>) {
+        if let Some(track) = self.get_current_track_mut() {
             track.points = points;
+            track.widths = widths;
         }
     }
 
@@ -125,12 +232,9 @@ impl TracksAsset {
     }
 
     pub fn delete_current_track(&mut self) {
-        match self.current_track_index {
-            None => return,
-            Some(index) => {
-                self.tracks.remove(index);
-                self.current_track_index = None;
-            }
+        if let Some(index) = self.current_track_index {
+            self.tracks.remove(index);
+            self.current_track_index = None;
         }
     }
 
@@ -148,16 +252,26 @@ impl TracksAsset {
         }
     }
 
+    /// Makes the track called `track_name` the current one and returns it.
+    pub fn select_track(&mut self, track_name: &str) -> Option<&RaceTrack> {
+        let index = self
+            .tracks
+            .iter()
+            .position(|track| track.track_name == track_name)?;
+        self.current_track_index = Some(index);
+        self.tracks.get(index)
+    }
+
     pub fn get_next_track(&mut self) -> Option<&RaceTrack> {
         match self.current_track_index {
             None => {
                 self.current_track_index = Some(0);
-                self.tracks.get(0)
+                self.tracks.first()
             }
             Some(index) => {
                 if index == self.tracks.len() - 1 {
                     self.current_track_index = Some(0);
-                    self.tracks.get(0)
+                    self.tracks.first()
                 } else {
                     self.current_track_index = Some(index + 1);
                     self.tracks.get(index + 1)
@@ -170,12 +284,12 @@ impl TracksAsset {
         match self.current_track_index {
             None => {
                 self.current_track_index = Some(0);
-                self.tracks.get(0)
+                self.tracks.first()
             }
             Some(index) => {
                 if index == 0 {
                     self.current_track_index = Some(self.tracks.len() - 1);
-                    self.tracks.get(self.tracks.len() - 1)
+                    self.tracks.last()
                 } else {
                     self.current_track_index = Some(index - 1);
                     self.tracks.get(index - 1)
@@ -194,9 +308,9 @@ pub enum TracksAssetLoaderError {
     /// An [IO](std::io) Error
     #[error("Could not load asset: {0}")]
     Io(#[from] std::io::Error),
-    /// A [JSON](json) Error
-    #[error("Could not parse JSON: {0}")]
-    JsonError(#[from] serde_json::Error),
+    /// A [JSON](json) Error, or a file that does not match the tracks format
+    #[error("{0}")]
+    JsonError(#[from] format::FormatError),
 }
 
 impl AssetLoader for TracksAssetLoader {
@@ -212,7 +326,10 @@ impl AssetLoader for TracksAssetLoader {
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
-        let custom_asset = serde_json::from_slice(&bytes).unwrap_or_default();
+        let custom_asset = format::from_slice(&bytes)?;
+        for broken in &custom_asset.broken_tracks {
+            warn!("Skipping broken {broken}");
+        }
 
         Ok(custom_asset)
     }
@@ -0,0 +1,257 @@
+//! Colliders and collision layers for a [`RaceTrack`].
+//!
+//! The road itself is not solid: walls run along both edges, and the road surface is a sensor that
+//! tells which cars are on the track. [`TrackLayer`] keeps walls and road sensors from interacting
+//! with anything but cars, and keeps the two levels of a [bridge](super::bridge) apart.
+
+use avian2d::prelude::{Collider, CollidingEntities, CollisionLayers, PhysicsLayer};
+use bevy::math::Vec2;
+use bevy::prelude::{Component, DetectChangesMut, Query, With};
+
+use super::bridge::Bridge;
+use super::mesh::TrackEdges;
+use super::surface::{SurfaceZone, TrackSide};
+
+#[derive(PhysicsLayer, Clone, Copy, Debug, Default)]
+pub enum TrackLayer {
+    #[default]
+    Default,
+    Wall,
+    RoadSensor,
+    Car,
+    /// The walls of a bridge, which only cars on the bridge hit.
+    BridgeWall,
+    /// Cars on a bridge, which do not hit cars or walls under it.
+    BridgeCar,
+}
+
+impl TrackLayer {
+    /// Walls only block cars on the ground.
+    pub fn wall() -> CollisionLayers {
+        CollisionLayers::new(TrackLayer::Wall, [TrackLayer::Car])
+    }
+
+    /// Bridge walls only block cars on the bridge.
+    pub fn bridge_wall() -> CollisionLayers {
+        CollisionLayers::new(TrackLayer::BridgeWall, [TrackLayer::BridgeCar])
+    }
+
+    /// Road sensors only detect cars, on either level.
+    pub fn road_sensor() -> CollisionLayers {
+        CollisionLayers::new(
+            TrackLayer::RoadSensor,
+            [TrackLayer::Car, TrackLayer::BridgeCar],
+        )
+    }
+
+    /// Cars on the ground hit walls and each other, and are seen by road sensors.
+    pub fn car() -> CollisionLayers {
+        CollisionLayers::new(
+            TrackLayer::Car,
+            [TrackLayer::Wall, TrackLayer::RoadSensor, TrackLayer::Car],
+        )
+    }
+
+    /// Cars on a bridge hit the bridge walls and each other, and are seen by road sensors.
+    pub fn bridge_car() -> CollisionLayers {
+        CollisionLayers::new(
+            TrackLayer::BridgeCar,
+            [
+                TrackLayer::BridgeWall,
+                TrackLayer::RoadSensor,
+                TrackLayer::BridgeCar,
+            ],
+        )
+    }
+}
+
+/// Marks anything racing on the track.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Car;
+
+/// Marks the sensor covering the road surface.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct RoadSensor;
+
+/// Whether a car is touching the road. Needs [`CollidingEntities`] on the same entity.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OnRoad(pub bool);
+
+/// The walls around the road, with the layers they go on. The walls along each edge are split
+/// where the track goes onto and off a [`Bridge`], and the ends of an open track are closed off by
+/// the walls around its caps.
+pub fn wall_colliders(edges: &TrackEdges, bridges: &[Bridge]) -> Vec<(Collider, CollisionLayers)> {
+    let on_bridge = edges
+        .distances
+        .iter()
+        .map(|&distance| {
+            bridges
+                .iter()
+                .any(|bridge| bridge.contains(distance, edges.length))
+        })
+        .collect::<Vec<_>>();
+
+    let mut walls = Vec::new();
+    for side in [&edges.left, &edges.right] {
+        for (points, bridge) in split_runs(side, &on_bridge, edges.closed) {
+            let layers = if bridge {
+                TrackLayer::bridge_wall()
+            } else {
+                TrackLayer::wall()
+            };
+            walls.push((Collider::polyline(points, None), layers));
+        }
+    }
+    for cap in [edges.start_cap(), edges.end_cap()].into_iter().flatten() {
+        walls.push((Collider::polyline(cap, None), TrackLayer::wall()));
+    }
+    walls
+}
+
+/// Splits a line of samples into runs that are either all on a bridge or all on the ground.
+/// Neighbouring runs share an end point so there are no gaps between them. A closed line is
+/// joined back up to its first point.
+fn split_runs(points: &[Vec2], on_bridge: &[bool], closed: bool) -> Vec<(Vec<Vec2>, bool)> {
+    let count = points.len().min(on_bridge.len());
+    if count == 0 {
+        return Vec::new();
+    }
+    // Start a closed line where a run starts, so no run is cut in two by the seam.
+    let first = if closed {
+        (0..count)
+            .find(|&i| on_bridge[i] != on_bridge[(i + count - 1) % count])
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    let steps = if closed { count + 1 } else { count };
+
+    let mut runs = Vec::new();
+    let mut run = vec![points[first]];
+    let mut bridge = on_bridge[first];
+    for step in 1..steps {
+        let i = (first + step) % count;
+        run.push(points[i]);
+        if on_bridge[i] != bridge {
+            runs.push((std::mem::replace(&mut run, vec![points[i]]), bridge));
+            bridge = on_bridge[i];
+        }
+    }
+    if run.len() > 1 {
+        runs.push((run, bridge));
+    }
+    runs
+}
+
+/// A sensor shape covering the road between the edges.
+pub fn road_collider(edges: &TrackEdges) -> Collider {
+    strip_collider(&edges.left, &edges.right, edges.closed)
+}
+
+/// A triangle strip between two matching lines of points, joining the ends if `closed`.
+pub fn strip_collider(left: &[Vec2], right: &[Vec2], closed: bool) -> Collider {
+    let count = left.len().min(right.len()) as u32;
+    let vertices = left
+        .iter()
+        .zip(right)
+        .flat_map(|(left, right)| [*left, *right])
+        .collect::<Vec<_>>();
+    let quads = if closed { count } else { count.saturating_sub(1) };
+    let indices = (0..quads)
+        .flat_map(|i| {
+            let (left, right) = (i * 2, i * 2 + 1);
+            let next = (i + 1) % count;
+            let (next_left, next_right) = (next * 2, next * 2 + 1);
+            [[left, right, next_left], [right, next_right, next_left]]
+        })
+        .collect::<Vec<_>>();
+    Collider::trimesh(vertices, indices)
+}
+
+/// A sensor shape covering the road from `start` to `end` along the centerline.
+pub fn span_collider(edges: &TrackEdges, start: f32, end: f32) -> Collider {
+    let (left, right): (Vec<_>, Vec<_>) = edges
+        .span(start, end)
+        .iter()
+        .map(|row| (row.left, row.right))
+        .unzip();
+    strip_collider(&left, &right, false)
+}
+
+/// A sensor shape covering a surface zone.
+pub fn surface_collider(edges: &TrackEdges, zone: &SurfaceZone) -> Collider {
+    let rows = edges.span(zone.start, zone.end);
+    let (left, right): (Vec<_>, Vec<_>) = rows
+        .iter()
+        .map(|row| match zone.side {
+            None => (row.left, row.right),
+            Some(TrackSide::Left) => (row.left, row.center),
+            Some(TrackSide::Right) => (row.center, row.right),
+        })
+        .unzip();
+    strip_collider(&left, &right, false)
+}
+
+/// Keeps [`OnRoad`] up to date from what each car is touching.
+pub fn update_on_road(
+    mut cars: Query<(&CollidingEntities, &mut OnRoad)>,
+    sensors: Query<(), With<RoadSensor>>,
+) {
+    for (colliding, mut on_road) in &mut cars {
+        let touching = colliding.iter().any(|&entity| sensors.contains(entity));
+        on_road.set_if_neq(OnRoad(touching));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use avian2d::prelude::{Gravity, LinearVelocity, PhysicsPlugins, RigidBody};
+    use bevy::prelude::*;
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    #[test]
+    fn cars_cannot_drive_through_walls() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            AssetPlugin::default(),
+            bevy::scene::ScenePlugin,
+            PhysicsPlugins::default(),
+        ))
+            .insert_resource(Gravity::ZERO)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                1.0 / 60.0,
+            )));
+        app.finish();
+        app.cleanup();
+        app.world_mut().spawn((
+            RigidBody::Static,
+            Collider::polyline(vec![Vec2::new(100.0, -200.0), Vec2::new(100.0, 200.0)], None),
+            TrackLayer::wall(),
+        ));
+        let car = app
+            .world_mut()
+            .spawn((
+                Car,
+                RigidBody::Dynamic,
+                Collider::circle(12.0),
+                TrackLayer::car(),
+                LinearVelocity(Vec2::new(400.0, 0.0)),
+                Transform::default(),
+            ))
+            .id();
+
+        // Two seconds, long enough to be well past the wall if nothing stopped the car.
+        for _ in 0..120 {
+            app.update();
+        }
+
+        let position = app.world().get::<Transform>(car).unwrap().translation;
+        assert!(position.x < 100.0, "the car went through the wall to {position}");
+    }
+}
@@ -0,0 +1,213 @@
+//! Adding and removing control points of a [`RaceTrack`].
+//!
+//! Control point `i` sits at curve parameter `t = i`, and distances along the centerline change
+//! with the shape of the curve, so adding or removing a point would move every gate, surface,
+//! bridge, prop and corner name to another part of the road. Instead, each of them is carried over
+//! by where it was between the two nearest control points both versions of the track share: a gate
+//! a third of the way along the road from one of those points to the next is a third of the way
+//! between the same two points afterwards.
+
+use bevy::math::Vec2;
+
+use super::RaceTrack;
+use super::arc_length::ArcLengthTable;
+
+impl RaceTrack {
+    /// Adds a control point before the one at `index`, or after the last one if `index` is the
+    /// number of points, keeping everything placed along the track where it was on the road.
+    pub fn insert_point(&mut self, index: usize, point: Vec2, width: f32) {
+        let old = self.clone();
+        let index = index.min(self.points.len());
+        self.widths = self.point_widths();
+        self.points.insert(index, point);
+        self.widths.insert(index, width);
+        let shared = (0..old.points.len())
+            .map(|k| (k, if k >= index { k + 1 } else { k }))
+            .collect::<Vec<_>>();
+        self.carry_over(&old, &shared);
+    }
+
+    /// Removes the control point at `index`, keeping everything placed along the track where it
+    /// was on the road. What was around the removed point ends up on the road joining its
+    /// neighbours.
+    pub fn remove_point(&mut self, index: usize) {
+        if index >= self.points.len() {
+            return;
+        }
+        let old = self.clone();
+        self.widths = self.point_widths();
+        self.points.remove(index);
+        self.widths.remove(index);
+        let shared = (0..old.points.len())
+            .filter(|&k| k != index)
+            .map(|k| (k, if k > index { k - 1 } else { k }))
+            .collect::<Vec<_>>();
+        self.carry_over(&old, &shared);
+    }
+
+    /// Moves everything placed along `old` to the same place on this track. `shared` pairs the
+    /// index of each control point in `old` with its index in this track.
+    fn carry_over(&mut self, old: &RaceTrack, shared: &[(usize, usize)]) {
+        let (Some(old_table), Some(new_table)) = (old.arc_length_table(), self.arc_length_table())
+        else {
+            // Without both curves there are no distances to keep, only the curve parameters to
+            // shift along with the points.
+            let map_t = |t: f32| {
+                shared
+                    .iter()
+                    .find(|&&(old_index, _)| old_index == t.floor() as usize)
+                    .map_or(t, |&(old_index, new_index)| t - old_index as f32 + new_index as f32)
+            };
+            self.map_features(map_t, |distance| distance);
+            return;
+        };
+
+        let anchors = shared
+            .iter()
+            .map(|&(old_index, new_index)| {
+                (
+                    old_table.distance_at_t(old_index as f32),
+                    new_table.distance_at_t(new_index as f32),
+                )
+            })
+            .collect::<Vec<_>>();
+        let closed = self.closed;
+        let map_distance = |distance| {
+            carry_distance(&anchors, distance, old_table.length(), new_table.length(), closed)
+        };
+        let map_t = |t| carry_t(&old_table, &new_table, map_distance, t);
+        self.map_features(map_t, map_distance);
+    }
+
+    /// Replaces every curve parameter stored on the track with `map_t` of it, and every distance
+    /// with `map_distance` of it.
+    fn map_features(&mut self, map_t: impl Fn(f32) -> f32, map_distance: impl Fn(f32) -> f32) {
+        self.start_line = map_t(self.start_line);
+        self.finish_line = map_t(self.finish_line);
+        for checkpoint in &mut self.checkpoints {
+            *checkpoint = map_t(*checkpoint);
+        }
+        for surface in &mut self.surfaces {
+            surface.start = map_distance(surface.start);
+            surface.end = map_distance(surface.end);
+        }
+        for bridge in &mut self.bridges {
+            bridge.start = map_distance(bridge.start);
+            bridge.end = map_distance(bridge.end);
+        }
+        for prop in &mut self.props {
+            prop.distance = map_distance(prop.distance);
+        }
+        for name in &mut self.corner_names {
+            name.distance = map_distance(name.distance);
+        }
+    }
+}
+
+/// Carries a curve parameter from the curve measured by `old` to the one measured by `new`.
+fn carry_t(
+    old: &ArcLengthTable,
+    new: &ArcLengthTable,
+    map_distance: impl Fn(f32) -> f32,
+    t: f32,
+) -> f32 {
+    new.t_at_distance(map_distance(old.distance_at_t(t)))
+}
+
+/// Carries `distance` along the old centerline to the new one, by interpolating between the
+/// `anchors`: the distances of the shared control points along the old and the new centerline.
+/// Distances past a whole lap of a closed track stay past it, so a surface running all the way
+/// round still does.
+fn carry_distance(
+    anchors: &[(f32, f32)],
+    distance: f32,
+    old_length: f32,
+    new_length: f32,
+    closed: bool,
+) -> f32 {
+    let (Some(&first), Some(&last)) = (anchors.first(), anchors.last()) else {
+        return distance;
+    };
+    let (laps, distance) = if closed && old_length > 0.0 {
+        let laps = (distance / old_length).floor();
+        (laps, distance - laps * old_length)
+    } else {
+        (0.0, distance)
+    };
+
+    let next = anchors.partition_point(|&(old, _)| old <= distance);
+    let (from, to) = match (next, closed) {
+        (0, false) => return first.1,
+        (0, true) => ((last.0 - old_length, last.1 - new_length), first),
+        (next, false) if next == anchors.len() => return last.1,
+        (next, true) if next == anchors.len() => {
+            (last, (first.0 + old_length, first.1 + new_length))
+        }
+        (next, _) => (anchors[next - 1], anchors[next]),
+    };
+    let span = to.0 - from.0;
+    let fraction = if span > f32::EPSILON {
+        (distance - from.0) / span
+    } else {
+        0.0
+    };
+    let carried = from.1 + (to.1 - from.1) * fraction;
+    if closed {
+        carried.rem_euclid(new_length) + laps * new_length
+    } else {
+        carried
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    fn closed_track() -> RaceTrack {
+        let points = vec![
+            vec2(0.0, 0.0),
+            vec2(400.0, 0.0),
+            vec2(600.0, 200.0),
+            vec2(400.0, 400.0),
+            vec2(0.0, 400.0),
+            vec2(-200.0, 200.0),
+        ];
+        RaceTrack {
+            widths: vec![40.0; points.len()],
+            points,
+            closed: true,
+            start_line: 3.5,
+            checkpoints: vec![4.25],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn inserting_a_point_before_the_start_line_keeps_the_gate_in_place() {
+        let mut track = closed_track();
+        let gate = track.start_line_gate().unwrap();
+        let checkpoint = track.checkpoint_gates()[0];
+
+        track.insert_point(1, vec2(200.0, -20.0), 40.0);
+
+        let moved = track.start_line_gate().unwrap();
+        assert!(moved.position.distance(gate.position) < 0.5, "{moved:?} != {gate:?}");
+        let moved = track.checkpoint_gates()[0];
+        assert!(moved.position.distance(checkpoint.position) < 0.5, "{moved:?} != {checkpoint:?}");
+    }
+
+    #[test]
+    fn removing_an_inserted_point_restores_the_track() {
+        let original = closed_track();
+        let mut track = original.clone();
+
+        track.insert_point(4, vec2(200.0, 420.0), 40.0);
+        track.remove_point(4);
+
+        assert_eq!(track.points, original.points);
+        assert!((track.start_line - original.start_line).abs() < 1e-3);
+        assert!((track.checkpoints[0] - original.checkpoints[0]).abs() < 1e-3);
+    }
+}
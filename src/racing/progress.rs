@@ -0,0 +1,205 @@
+//! How far each car has got in a race, and when it has finished.
+//!
+//! Progress is measured along the centerline from the start line. Closed tracks are raced for the
+//! laps of the race, crossing the start line at the end of each. Open tracks are raced once, from
+//! the start line to the finish line, with no laps at all. See [`RaceMode`].
+
+use bevy::math::Vec2;
+use bevy::prelude::Component;
+
+use super::RaceTrack;
+use super::arc_length::ArcLengthTable;
+use super::gates::RaceMode;
+
+/// How far along the centerline from where a car was last measured it is looked for. Far more
+/// than a car covers between two frames, and far less than the distance between the two branches
+/// of a track crossing itself.
+const PROGRESS_SEARCH_RANGE: f32 = 200.0;
+
+/// A track measured for racing on, so that progress does not have to measure it every frame.
+#[derive(Debug, Clone)]
+pub struct RaceCourse {
+    table: ArcLengthTable,
+    mode: RaceMode,
+    /// The distance of the start line along the centerline.
+    start: f32,
+    /// 1 if the race runs the same way as the centerline, -1 if a sprint runs against it.
+    direction: f32,
+    /// The distance from the start line to the finish, see [`RaceTrack::race_length`].
+    length: f32,
+}
+
+impl RaceCourse {
+    /// Measures `track` for a race of `laps` laps, which sprints ignore. `None` if the track has
+    /// too few points for a curve.
+    pub fn new(track: &RaceTrack, laps: u32) -> Option<Self> {
+        let table = track.arc_length_table()?;
+        let mode = track.race_mode();
+        let start = table.distance_at_t(track.start_line);
+        let direction = match mode {
+            RaceMode::Laps => 1.0,
+            RaceMode::Sprint if table.distance_at_t(track.finish_line) < start => -1.0,
+            RaceMode::Sprint => 1.0,
+        };
+        Some(Self {
+            length: track.race_length(laps),
+            table,
+            mode,
+            start,
+            direction,
+        })
+    }
+
+    /// Moves `progress` on to a car now at `position`.
+    pub fn advance(&self, progress: &mut RaceProgress, position: Vec2) {
+        if progress.finished {
+            return;
+        }
+        let distance = match progress.last {
+            Some(last) => {
+                self.table
+                    .closest_point_near(position, last, PROGRESS_SEARCH_RANGE)
+                    .distance
+            }
+            None => self.table.closest_point(position).distance,
+        };
+        progress.covered = match self.mode {
+            // A car never gets half a lap in one frame, so the shorter way round from where it was
+            // is the way it went.
+            RaceMode::Laps => {
+                let lap = self.table.length();
+                let from = progress.last.unwrap_or(self.start);
+                let moved = (distance - from + lap / 2.0).rem_euclid(lap) - lap / 2.0;
+                if progress.last.is_some() {
+                    progress.covered + moved
+                } else {
+                    moved
+                }
+            }
+            RaceMode::Sprint => (distance - self.start) * self.direction,
+        };
+        progress.last = Some(distance);
+        progress.finished = progress.covered >= self.length;
+    }
+}
+
+/// How far a car has got in the race.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct RaceProgress {
+    /// The distance covered from the start line, negative on the grid behind it.
+    pub covered: f32,
+    /// Where the car was along the centerline when last measured.
+    last: Option<f32>,
+    /// Whether the car has crossed the finish line, at the end of the last lap or of the sprint.
+    pub finished: bool,
+}
+
+impl RaceProgress {
+    /// Carries on measuring from `distance` along a new version of the track, keeping the distance
+    /// covered so far.
+    pub fn remeasure_from(&mut self, distance: f32) {
+        self.last = Some(distance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    fn track(closed: bool) -> RaceTrack {
+        let points = vec![
+            vec2(0.0, 0.0),
+            vec2(400.0, 0.0),
+            vec2(600.0, 200.0),
+            vec2(400.0, 400.0),
+            vec2(0.0, 400.0),
+        ];
+        RaceTrack {
+            widths: vec![40.0; points.len()],
+            points,
+            closed,
+            start_line: 0.5,
+            finish_line: 3.5,
+            ..Default::default()
+        }
+    }
+
+    /// Drives along the centerline of `track` from `from` to `to`, in small steps.
+    fn drive(course: &RaceCourse, track: &RaceTrack, progress: &mut RaceProgress, from: f32, to: f32) {
+        let table = track.arc_length_table().unwrap();
+        let steps = ((to - from).abs() / 10.0).ceil() as usize;
+        for step in 0..=steps {
+            let distance = from + (to - from) * step as f32 / steps as f32;
+            course.advance(progress, table.position_at(distance.rem_euclid(table.length())));
+        }
+    }
+
+    #[test]
+    fn a_sprint_finishes_at_the_finish_line() {
+        let track = track(false);
+        let table = track.arc_length_table().unwrap();
+        let start = table.distance_at_t(track.start_line);
+        let finish = table.distance_at_t(track.finish_line);
+        let course = RaceCourse::new(&track, 3).unwrap();
+        let mut progress = RaceProgress::default();
+
+        drive(&course, &track, &mut progress, start, finish - 5.0);
+        assert!(!progress.finished);
+        drive(&course, &track, &mut progress, finish - 5.0, finish + 5.0);
+        assert!(progress.finished);
+    }
+
+    #[test]
+    fn progress_keeps_to_its_branch_through_a_crossing() {
+        // A figure eight, crossing itself in the middle.
+        let points = (0..8)
+            .map(|k| {
+                let angle = std::f32::consts::PI / 8.0 + k as f32 * std::f32::consts::PI / 4.0;
+                vec2(400.0 * angle.sin(), 200.0 * (2.0 * angle).sin())
+            })
+            .collect::<Vec<_>>();
+        let track = RaceTrack {
+            widths: vec![60.0; points.len()],
+            points,
+            closed: true,
+            ..Default::default()
+        };
+        let table = track.arc_length_table().unwrap();
+        let start = table.distance_at_t(track.start_line);
+        let course = RaceCourse::new(&track, 2).unwrap();
+        let mut progress = RaceProgress::default();
+
+        // Off the centerline, so the other branch is the closer one right at the crossing.
+        let steps = (table.length() * 2.0 / 10.0) as usize;
+        for step in 0..steps {
+            let covered = step as f32 * 10.0;
+            let distance = start + covered;
+            let position = table.position_at(distance) + table.normal_at(distance) * 15.0;
+            course.advance(&mut progress, position);
+            assert!((progress.covered - covered).abs() < 5.0, "{progress:?} at {covered}");
+        }
+        assert!(!progress.finished);
+    }
+
+    #[test]
+    fn a_lap_race_finishes_after_its_laps() {
+        let track = track(true);
+        let table = track.arc_length_table().unwrap();
+        let start = table.distance_at_t(track.start_line);
+        let course = RaceCourse::new(&track, 2).unwrap();
+        let mut progress = RaceProgress::default();
+
+        drive(&course, &track, &mut progress, start - 30.0, start + table.length() + 10.0);
+        assert!(!progress.finished, "{progress:?}");
+        drive(
+            &course,
+            &track,
+            &mut progress,
+            start + table.length() + 10.0,
+            start + table.length() * 2.0 + 5.0,
+        );
+        assert!(progress.finished, "{progress:?}");
+    }
+}
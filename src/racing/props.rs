@@ -0,0 +1,124 @@
+//! Props placed along a [`RaceTrack`]: tyre stacks, barrels, barriers and explosive tanks.
+//!
+//! Props are positioned in track space, a distance along the centerline and an offset to the side
+//! of it, so they move with the road when control points are moved.
+
+use avian2d::prelude::Collider;
+use bevy::color::Color;
+use bevy::math::{Quat, Vec2};
+use bevy::prelude::{Reflect, Transform};
+use serde::{Deserialize, Serialize};
+
+use super::RaceTrack;
+use super::arc_length::{ArcLengthTable, TrackPosition};
+
+/// How close to a prop, in world units, a click has to be to pick it in the editor.
+pub const PROP_PICK_RADIUS: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+#[serde(rename_all = "snake_case")]
+pub enum PropKind {
+    TyreStack,
+    Barrel,
+    Barrier,
+    ExplosiveTank,
+}
+
+impl PropKind {
+    pub const ALL: [PropKind; 4] = [
+        PropKind::TyreStack,
+        PropKind::Barrel,
+        PropKind::Barrier,
+        PropKind::ExplosiveTank,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PropKind::TyreStack => "Tyre Stack",
+            PropKind::Barrel => "Barrel",
+            PropKind::Barrier => "Barrier",
+            PropKind::ExplosiveTank => "Explosive Tank",
+        }
+    }
+
+    /// The size of the prop. Round props are as wide as they are long.
+    pub fn size(self) -> Vec2 {
+        match self {
+            PropKind::TyreStack => Vec2::splat(14.0),
+            PropKind::Barrel => Vec2::splat(10.0),
+            PropKind::Barrier => Vec2::new(8.0, 40.0),
+            PropKind::ExplosiveTank => Vec2::splat(18.0),
+        }
+    }
+
+    pub fn is_round(self) -> bool {
+        !matches!(self, PropKind::Barrier)
+    }
+
+    pub fn collider(self) -> Collider {
+        let size = self.size();
+        if self.is_round() {
+            Collider::circle(size.x / 2.0)
+        } else {
+            Collider::rectangle(size.x, size.y)
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PropKind::TyreStack => Color::srgb(0.15, 0.15, 0.15),
+            PropKind::Barrel => Color::srgb(0.2, 0.4, 0.8),
+            PropKind::Barrier => Color::srgb(0.9, 0.9, 0.9),
+            PropKind::ExplosiveTank => Color::srgb(0.9, 0.15, 0.1),
+        }
+    }
+}
+
+/// A prop placed along the track.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct TrackProp {
+    pub kind: PropKind,
+    /// The distance along the centerline.
+    pub distance: f32,
+    /// The distance from the centerline, positive to the left of the direction of travel.
+    pub lateral_offset: f32,
+    /// The rotation in radians, relative to the direction of travel.
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+impl TrackProp {
+    pub fn at(kind: PropKind, position: TrackPosition) -> Self {
+        Self {
+            kind,
+            distance: position.distance,
+            lateral_offset: position.lateral_offset,
+            rotation: 0.0,
+        }
+    }
+
+    /// Where the prop is in the world, on a track measured by `table`.
+    pub fn position(&self, table: &ArcLengthTable) -> Vec2 {
+        table.position_at(self.distance) + table.normal_at(self.distance) * self.lateral_offset
+    }
+
+    /// A transform placing the prop in the world, turned to follow the track.
+    pub fn transform(&self, table: &ArcLengthTable) -> Transform {
+        let angle = table.tangent_at(self.distance).to_angle() + self.rotation;
+        Transform::from_translation(self.position(table).extend(0.0))
+            .with_rotation(Quat::from_rotation_z(angle))
+    }
+}
+
+impl RaceTrack {
+    /// The index of the prop closest to `position`, if any is within [`PROP_PICK_RADIUS`].
+    pub fn prop_near(&self, table: &ArcLengthTable, position: Vec2) -> Option<usize> {
+        self.props
+            .iter()
+            .enumerate()
+            .map(|(index, prop)| (index, prop.position(table).distance(position)))
+            .filter(|(_, distance)| *distance <= PROP_PICK_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
@@ -0,0 +1,248 @@
+//! The racing line: the path through a [`RaceTrack`] a driver would take to keep as much speed as
+//! possible, used by the AI and by the "show racing line" assist.
+//!
+//! The line is found by minimising its curvature within the road. It starts out on the centerline,
+//! sampled evenly along it, and is relaxed over and over, each sample moving sideways towards the
+//! midpoint of its neighbours and being kept [`RACING_LINE_MARGIN`] from the edges. That pulls the
+//! line to the outside before a corner, to the apex in the middle of it and back out after it.
+
+use bevy::color::Color;
+use bevy::math::Vec2;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::{Gizmos, Resource};
+
+use super::RaceTrack;
+use super::arc_length::ArcLengthTable;
+
+/// The distance along the centerline between samples of the racing line.
+pub const RACING_LINE_SPACING: f32 = 20.0;
+
+/// How close to the edge of the road the racing line is allowed to go.
+pub const RACING_LINE_MARGIN: f32 = 8.0;
+
+/// How many times the line is relaxed. More passes let the line settle over longer corners.
+const RELAXATION_PASSES: usize = 400;
+
+/// A racing line, stored as sideways offsets from the centerline at evenly spaced distances.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RacingLine {
+    /// Whether the line joins back up with its start.
+    pub closed: bool,
+    /// The distances along the centerline the line is sampled at.
+    pub distances: Vec<f32>,
+    /// The distance of the line from the centerline at each sample, positive to the left.
+    pub offsets: Vec<f32>,
+    /// The line in the world at each sample.
+    pub points: Vec<Vec2>,
+    /// The length of the centerline.
+    pub length: f32,
+}
+
+impl RacingLine {
+    /// Computes the minimum curvature line through `track`, measured by `table`.
+    pub fn solve(track: &RaceTrack, table: &ArcLengthTable) -> Self {
+        let closed = table.is_closed();
+        let length = table.length();
+        let distances = table
+            .uniform_params(RACING_LINE_SPACING)
+            .into_iter()
+            .map(|t| table.distance_at_t(t))
+            .collect::<Vec<_>>();
+        let centers = distances
+            .iter()
+            .map(|&distance| table.position_at(distance))
+            .collect::<Vec<_>>();
+        let normals = distances
+            .iter()
+            .map(|&distance| table.normal_at(distance))
+            .collect::<Vec<_>>();
+        let limits = distances
+            .iter()
+            .map(|&distance| {
+                let width = track.width_at(table.t_at_distance(distance));
+                (width / 2.0 - RACING_LINE_MARGIN).max(0.0)
+            })
+            .collect::<Vec<_>>();
+
+        let count = distances.len();
+        let mut offsets = vec![0.0; count];
+        // The ends of an open track stay on the centerline, where the cars start and finish.
+        let movable = if closed {
+            0..count
+        } else {
+            1..count.saturating_sub(1)
+        };
+        for _ in 0..RELAXATION_PASSES {
+            for i in movable.clone() {
+                let previous = (i + count - 1) % count;
+                let next = (i + 1) % count;
+                let midpoint = (centers[previous]
+                    + normals[previous] * offsets[previous]
+                    + centers[next]
+                    + normals[next] * offsets[next])
+                    / 2.0;
+                offsets[i] = (midpoint - centers[i])
+                    .dot(normals[i])
+                    .clamp(-limits[i], limits[i]);
+            }
+        }
+
+        let points = centers
+            .iter()
+            .zip(&normals)
+            .zip(&offsets)
+            .map(|((&center, &normal), &offset)| center + normal * offset)
+            .collect();
+        Self {
+            closed,
+            distances,
+            offsets,
+            points,
+            length,
+        }
+    }
+
+    /// The offset of the line from the centerline at `distance`, interpolated between samples.
+    pub fn offset_at(&self, distance: f32) -> f32 {
+        let count = self.distances.len();
+        if count == 0 || self.length <= 0.0 {
+            return 0.0;
+        }
+        let distance = if self.closed {
+            distance.rem_euclid(self.length)
+        } else {
+            distance.clamp(0.0, self.length)
+        };
+        let next = self
+            .distances
+            .partition_point(|&sample| sample <= distance);
+        if next == 0 {
+            return self.offsets[0];
+        }
+        let previous = next - 1;
+        let (next, next_distance) = match self.distances.get(next) {
+            Some(&next_distance) => (next, next_distance),
+            None if self.closed => (0, self.length),
+            None => return self.offsets[previous],
+        };
+        let span = next_distance - self.distances[previous];
+        let fraction = if span > f32::EPSILON {
+            (distance - self.distances[previous]) / span
+        } else {
+            0.0
+        };
+        self.offsets[previous] + (self.offsets[next] - self.offsets[previous]) * fraction
+    }
+
+    /// Where the line is in the world at `distance` along a centerline measured by `table`.
+    pub fn position_at(&self, table: &ArcLengthTable, distance: f32) -> Vec2 {
+        table.position_at(distance) + table.normal_at(distance) * self.offset_at(distance)
+    }
+
+    /// Draws the line with gizmos.
+    pub fn draw(&self, gizmos: &mut Gizmos, color: Color) {
+        let first = self.points.first().filter(|_| self.closed).copied();
+        gizmos.linestrip_2d(self.points.iter().copied().chain(first), color);
+    }
+}
+
+/// Racing lines by track name, each kept along with the version of the track it was solved for
+/// and solved again only when that track changes.
+#[derive(Resource, Debug, Default)]
+pub struct RacingLineCache(HashMap<String, (RaceTrack, RacingLine)>);
+
+impl RacingLineCache {
+    /// The racing line of `track`, solving it if the track is new or has changed. `None` if the
+    /// track has too few points for a curve.
+    pub fn get(&mut self, track: &RaceTrack) -> Option<&RacingLine> {
+        let stale = self
+            .0
+            .get(&track.track_name)
+            .is_none_or(|(cached, _)| cached != track);
+        if stale {
+            let line = RacingLine::solve(track, &track.arc_length_table()?);
+            self.0
+                .insert(track.track_name.clone(), (track.clone(), line));
+        }
+        self.0.get(&track.track_name).map(|(_, line)| line)
+    }
+}
+
+/// Whether the racing line is drawn, in the editor and in races.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShowRacingLine(pub bool);
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, TAU};
+
+    use bevy::math::vec2;
+
+    use super::*;
+
+    fn solve(track: &RaceTrack) -> RacingLine {
+        RacingLine::solve(track, &track.arc_length_table().unwrap())
+    }
+
+    #[test]
+    fn a_loop_is_driven_on_the_inside_within_the_road() {
+        // Anticlockwise, so the inside is on the left.
+        let points = (0..12)
+            .map(|i| Vec2::from_angle(i as f32 * TAU / 12.0) * 200.0)
+            .collect::<Vec<_>>();
+        let track = RaceTrack {
+            widths: vec![60.0; points.len()],
+            points,
+            closed: true,
+            ..Default::default()
+        };
+        let limit = 60.0 / 2.0 - RACING_LINE_MARGIN;
+        for offset in solve(&track).offsets {
+            assert!(offset <= limit + 1e-3, "{offset}");
+            assert!(offset > limit * 0.9, "{offset}");
+        }
+    }
+
+    #[test]
+    fn a_corner_is_cut_to_its_apex() {
+        // A straight, a left turn through a right angle and another straight.
+        let mut points = (0..8).map(|i| vec2(i as f32 * 50.0, 0.0)).collect::<Vec<_>>();
+        points.extend((0..=4).map(|i| {
+            let angle = -FRAC_PI_2 + i as f32 * FRAC_PI_2 / 4.0;
+            vec2(400.0, 100.0) + Vec2::from_angle(angle) * 100.0
+        }));
+        points.extend((1..8).map(|i| vec2(500.0, 100.0 + i as f32 * 50.0)));
+        let track = RaceTrack {
+            widths: vec![80.0; points.len()],
+            points,
+            closed: false,
+            ..Default::default()
+        };
+        let line = solve(&track);
+        let corner = track.corners()[0];
+
+        // The ends stay on the centerline, where the cars start and finish.
+        assert_eq!(line.offsets.first(), Some(&0.0));
+        assert_eq!(line.offsets.last(), Some(&0.0));
+        // Right up against the inside of the corner at its apex.
+        let limit = 80.0 / 2.0 - RACING_LINE_MARGIN;
+        assert!(line.offset_at(corner.apex) > limit * 0.9, "{line:?}");
+        assert!(line.offsets.iter().all(|offset| offset.abs() <= limit + 1e-3), "{line:?}");
+    }
+
+    #[test]
+    fn offsets_are_interpolated_between_samples() {
+        let line = RacingLine {
+            closed: true,
+            distances: vec![0.0, 10.0, 20.0],
+            offsets: vec![0.0, 4.0, -2.0],
+            points: Vec::new(),
+            length: 30.0,
+        };
+        assert_eq!(line.offset_at(5.0), 2.0);
+        assert_eq!(line.offset_at(15.0), 1.0);
+        // Across the start of a closed line, and wrapped round it.
+        assert_eq!(line.offset_at(25.0), -1.0);
+        assert_eq!(line.offset_at(35.0), 2.0);
+    }
+}
@@ -0,0 +1,193 @@
+//! Share codes: a whole [`RaceTrack`] as a short line of text that can be pasted into chat.
+//!
+//! A code is the track written as a one-track `.tracks` document with every number rounded to
+//! [`SHARE_CODE_DECIMALS`] decimals, compressed with deflate, prefixed with a CRC-32 checksum of
+//! the document and encoded as URL-safe base64 after [`SHARE_CODE_PREFIX`]. Because the document
+//! carries its format version, codes made by older versions of the game are upgraded by the same
+//! migrations as files are.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+use serde_json::{Number, Value};
+use thiserror::Error;
+
+use super::format::{self, FormatError};
+use super::{RaceTrack, TracksAsset};
+
+/// Every share code starts with this, which also versions the code itself.
+pub const SHARE_CODE_PREFIX: &str = "RT1-";
+
+/// How many decimals numbers in a share code are rounded to.
+pub const SHARE_CODE_DECIMALS: i32 = 2;
+
+/// The deflate compression level, from 0 to 10.
+const COMPRESSION_LEVEL: u8 = 9;
+
+/// The largest document a code may expand to, so a malicious code cannot eat all memory.
+const MAX_DOCUMENT_BYTES: usize = 1 << 20;
+
+/// The length of the checksum in front of the compressed document.
+const CHECKSUM_BYTES: usize = 4;
+
+#[derive(Debug, Error)]
+pub enum ShareCodeError {
+    #[error("Track codes start with \"{}\"", SHARE_CODE_PREFIX)]
+    MissingPrefix,
+    #[error("The track code is not valid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("The track code is too short")]
+    TooShort,
+    #[error("The track code could not be decompressed")]
+    Decompress,
+    #[error("The track code is damaged, its checksum does not match")]
+    ChecksumMismatch,
+    #[error("{0}")]
+    Format(#[from] FormatError),
+    #[error("The track code does not contain a track")]
+    NoTrack,
+}
+
+impl RaceTrack {
+    /// The share code of the track.
+    pub fn share_code(&self) -> Result<String, serde_json::Error> {
+        let mut document = serde_json::to_value(TracksAsset {
+            tracks: vec![self.clone()],
+            current_track_index: Some(0),
+            ..Default::default()
+        })?;
+        quantize(&mut document);
+        let json = serde_json::to_vec(&document)?;
+
+        let mut bytes = crc32fast::hash(&json).to_be_bytes().to_vec();
+        bytes.extend(compress_to_vec(&json, COMPRESSION_LEVEL));
+        Ok(format!("{SHARE_CODE_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes)))
+    }
+
+    /// Reads a track back from a share code. Whitespace around and inside the code is ignored, so
+    /// codes that were wrapped over several lines still work.
+    pub fn from_share_code(code: &str) -> Result<Self, ShareCodeError> {
+        let code = code
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        let encoded = code
+            .strip_prefix(SHARE_CODE_PREFIX)
+            .ok_or(ShareCodeError::MissingPrefix)?;
+        let bytes = URL_SAFE_NO_PAD.decode(encoded)?;
+        if bytes.len() < CHECKSUM_BYTES {
+            return Err(ShareCodeError::TooShort);
+        }
+        let (checksum, compressed) = bytes.split_at(CHECKSUM_BYTES);
+        let json = decompress_to_vec_with_limit(compressed, MAX_DOCUMENT_BYTES)
+            .map_err(|_| ShareCodeError::Decompress)?;
+        if crc32fast::hash(&json).to_be_bytes() != checksum {
+            return Err(ShareCodeError::ChecksumMismatch);
+        }
+
+        format::from_slice(&json)?
+            .tracks
+            .into_iter()
+            .next()
+            .ok_or(ShareCodeError::NoTrack)
+    }
+}
+
+/// Rounds every number in `value` to [`SHARE_CODE_DECIMALS`] decimals, writing whole numbers
+/// without a fraction.
+fn quantize(value: &mut Value) {
+    match value {
+        Value::Number(number) => {
+            let Some(float) = number.as_f64().filter(|_| number.is_f64()) else {
+                return;
+            };
+            let scale = 10f64.powi(SHARE_CODE_DECIMALS);
+            let rounded = (float * scale).round() / scale;
+            if rounded.fract() == 0.0 {
+                *number = Number::from(rounded as i64);
+            } else if let Some(rounded) = Number::from_f64(rounded) {
+                *number = rounded;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(quantize),
+        Value::Object(map) => map.values_mut().for_each(quantize),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+
+    /// A track whose numbers all survive rounding to [`SHARE_CODE_DECIMALS`] decimals.
+    fn track() -> RaceTrack {
+        RaceTrack {
+            track_name: "Shared".to_string(),
+            points: vec![
+                vec2(0.0, 0.0),
+                vec2(300.5, 0.0),
+                vec2(300.0, 200.25),
+                vec2(-10.75, 180.0),
+            ],
+            widths: vec![40.0, 45.5, 50.0, 40.0],
+            start_line: 0.5,
+            checkpoints: vec![1.25, 2.5],
+            ..Default::default()
+        }
+    }
+
+    /// The bytes of `code` after its prefix.
+    fn decode(code: &str) -> Vec<u8> {
+        URL_SAFE_NO_PAD
+            .decode(code.strip_prefix(SHARE_CODE_PREFIX).unwrap())
+            .unwrap()
+    }
+
+    fn encode(bytes: &[u8]) -> String {
+        format!("{SHARE_CODE_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    #[test]
+    fn a_track_survives_its_share_code() {
+        let track = track();
+        let code = track.share_code().unwrap();
+        assert_eq!(RaceTrack::from_share_code(&code).unwrap(), track);
+
+        // Wrapped over several lines when pasted into chat.
+        let (head, tail) = code.split_at(code.len() / 2);
+        let wrapped = format!("  {head}\n{tail} \n");
+        assert_eq!(RaceTrack::from_share_code(&wrapped).unwrap(), track);
+    }
+
+    #[test]
+    fn a_damaged_checksum_is_caught() {
+        let mut bytes = decode(&track().share_code().unwrap());
+        bytes[0] ^= 0xff;
+        assert!(matches!(
+            RaceTrack::from_share_code(&encode(&bytes)),
+            Err(ShareCodeError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn a_cut_off_code_does_not_decompress() {
+        let bytes = decode(&track().share_code().unwrap());
+        let cut = &bytes[..CHECKSUM_BYTES + (bytes.len() - CHECKSUM_BYTES) / 2];
+        assert!(matches!(
+            RaceTrack::from_share_code(&encode(cut)),
+            Err(ShareCodeError::Decompress)
+        ));
+    }
+
+    #[test]
+    fn a_code_without_its_prefix_is_refused() {
+        let code = track().share_code().unwrap();
+        assert!(matches!(
+            RaceTrack::from_share_code(&code[SHARE_CODE_PREFIX.len()..]),
+            Err(ShareCodeError::MissingPrefix)
+        ));
+    }
+}
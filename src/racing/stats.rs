@@ -0,0 +1,278 @@
+//! Statistics about a [`RaceTrack`] and an overall difficulty rating, shown in the editor and when
+//! picking a track, and used to put the races of a cup in order.
+
+use std::fmt;
+
+use super::arc_length::{ArcLengthTable, TRACK_SAMPLE_SPACING};
+use super::corners::{cornering_speed, find_corners, longest_straight, tightest_radius};
+use super::surface::{TrackSide, surface_at};
+use super::{DEFAULT_TRACK_WIDTH, MIN_TRACK_WIDTH, RaceTrack};
+
+/// The car lap times are estimated for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferenceCar {
+    /// Top speed on asphalt, in units per second.
+    pub top_speed: f32,
+    /// How quickly the car speeds up, in units per second squared.
+    pub acceleration: f32,
+    /// How quickly the car slows down.
+    pub braking: f32,
+    /// How much sideways acceleration the tyres can take on asphalt before sliding.
+    pub lateral_acceleration: f32,
+}
+
+/// A car like the player's.
+pub const REFERENCE_CAR: ReferenceCar = ReferenceCar {
+    top_speed: 400.0,
+    acceleration: 300.0,
+    braking: 600.0,
+    lateral_acceleration: 900.0,
+};
+
+/// Corners per thousand units of track that count as fully twisty for the difficulty rating.
+const TWISTY_CORNERS_PER_THOUSAND: f32 = 8.0;
+
+/// A corner this tight counts as fully tight for the difficulty rating.
+const TIGHT_RADIUS: f32 = 60.0;
+
+/// How much each part of the rating counts towards the difficulty.
+const TWISTINESS_WEIGHT: f32 = 0.35;
+const TIGHTNESS_WEIGHT: f32 = 0.35;
+const NARROWNESS_WEIGHT: f32 = 0.2;
+const HAZARD_WEIGHT: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackStats {
+    /// The length of the centerline.
+    pub length: f32,
+    pub corners: usize,
+    /// The radius of the tightest corner, or `None` if there are no corners.
+    pub tightest_radius: Option<f32>,
+    /// The longest stretch between corners.
+    pub longest_straight: f32,
+    /// The time [`REFERENCE_CAR`] takes to drive the track once, in seconds. A flying lap on a
+    /// closed track, from a standing start on an open one.
+    pub lap_time: f32,
+    /// How hard the track is, from 0 to 10.
+    pub difficulty: f32,
+}
+
+impl fmt::Display for TrackStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Length: {:.0}", self.length)?;
+        write!(f, "Corners: {}", self.corners)?;
+        if let Some(radius) = self.tightest_radius {
+            write!(f, " (tightest radius {radius:.0})")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Longest straight: {:.0}", self.longest_straight)?;
+        writeln!(f, "Lap time: {}", format_time(self.lap_time))?;
+        write!(f, "Difficulty: {:.1}/10", self.difficulty)
+    }
+}
+
+impl TrackStats {
+    /// The statistics on one line, for lists of tracks.
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.0} long, {} corners, {}, difficulty {:.1}",
+            self.length,
+            self.corners,
+            format_time(self.lap_time),
+            self.difficulty
+        )
+    }
+}
+
+/// Formats `seconds` as minutes, seconds and tenths, like `1:23.4`.
+pub fn format_time(seconds: f32) -> String {
+    let tenths = (seconds * 10.0).round() as u32;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+impl RaceTrack {
+    /// Measures the track, or `None` if it has too few points for a curve.
+    pub fn stats(&self) -> Option<TrackStats> {
+        let table = self.arc_length_table()?;
+        let corners = find_corners(&table);
+        let length = table.length();
+        let tightest_radius = tightest_radius(&corners);
+
+        let twistiness = (corners.len() as f32 * 1000.0
+            / length.max(f32::EPSILON)
+            / TWISTY_CORNERS_PER_THOUSAND)
+            .min(1.0);
+        let tightness = tightest_radius.map_or(0.0, |radius| (TIGHT_RADIUS / radius).min(1.0));
+        let mean_width = self.mean_width(&table);
+        let narrowness = ((2.0 * DEFAULT_TRACK_WIDTH - mean_width)
+            / (2.0 * DEFAULT_TRACK_WIDTH - MIN_TRACK_WIDTH))
+            .clamp(0.0, 1.0);
+        let hazard = self.hazard_fraction(&table);
+        let difficulty = 10.0
+            * (TWISTINESS_WEIGHT * twistiness
+                + TIGHTNESS_WEIGHT * tightness
+                + NARROWNESS_WEIGHT * narrowness
+                + HAZARD_WEIGHT * hazard);
+
+        Some(TrackStats {
+            length,
+            corners: corners.len(),
+            tightest_radius,
+            longest_straight: longest_straight(&corners, length, table.is_closed()),
+            lap_time: self.lap_time(&table, &REFERENCE_CAR),
+            difficulty,
+        })
+    }
+
+    /// The difficulty of the track, or 0 if it has too few points for a curve.
+    pub fn difficulty(&self) -> f32 {
+        self.stats().map_or(0.0, |stats| stats.difficulty)
+    }
+
+    /// The time `car` takes to drive the track once, in seconds. The car goes as fast as it can
+    /// through each bend and brakes just in time for the next.
+    pub fn lap_time(&self, table: &ArcLengthTable, car: &ReferenceCar) -> f32 {
+        let params = table.uniform_params(TRACK_SAMPLE_SPACING);
+        let count = params.len();
+        let length = table.length();
+        let closed = table.is_closed();
+        let steps = if closed { count } else { count - 1 };
+        let step = length / steps as f32;
+
+        let limits = params
+            .iter()
+            .map(|&t| {
+                let distance = table.distance_at_t(t);
+                let (grip, top_speed) = self.best_surface(distance, length);
+                let radius = table.curvature_at_t(t).abs().recip();
+                (car.top_speed * top_speed)
+                    .min(cornering_speed(radius, car.lateral_acceleration * grip))
+            })
+            .collect::<Vec<_>>();
+
+        // Speed up out of each bend and brake into the next. Going round a closed track twice lets
+        // the speed carry over the start line.
+        let laps = if closed { 2 } else { 1 };
+        let mut speeds = limits.clone();
+        if !closed {
+            speeds[0] = 0.0;
+        }
+        for i in 1..count * laps {
+            let (previous, current) = ((i - 1) % count, i % count);
+            let reachable = (speeds[previous].powi(2) + 2.0 * car.acceleration * step).sqrt();
+            speeds[current] = speeds[current].min(reachable);
+        }
+        for i in (0..count * laps - 1).rev() {
+            let (current, next) = (i % count, (i + 1) % count);
+            let reachable = (speeds[next].powi(2) + 2.0 * car.braking * step).sqrt();
+            speeds[current] = speeds[current].min(reachable);
+        }
+
+        (0..steps)
+            .map(|i| {
+                let average = (speeds[i] + speeds[(i + 1) % count]) / 2.0;
+                step / average.max(f32::EPSILON)
+            })
+            .sum()
+    }
+
+    /// The grip and top speed factor of the better side of the road at `distance`.
+    fn best_surface(&self, distance: f32, length: f32) -> (f32, f32) {
+        [TrackSide::Left, TrackSide::Right]
+            .map(|side| surface_at(&self.surfaces, distance, side, length))
+            .into_iter()
+            .map(|kind| kind.map_or((1.0, 1.0), |kind| (kind.grip(), kind.top_speed())))
+            .fold((0.0, 0.0), |(grip, top_speed), (side_grip, side_top_speed)| {
+                (grip.max(side_grip), top_speed.max(side_top_speed))
+            })
+    }
+
+    /// The average width of the road along the centerline.
+    fn mean_width(&self, table: &ArcLengthTable) -> f32 {
+        let params = table.uniform_params(TRACK_SAMPLE_SPACING);
+        let Some(width_curve) = self.form_width_curve() else {
+            return DEFAULT_TRACK_WIDTH;
+        };
+        params
+            .iter()
+            .map(|&t| width_curve.position(t).max(MIN_TRACK_WIDTH))
+            .sum::<f32>()
+            / params.len() as f32
+    }
+
+    /// The fraction of the road covered by surfaces with less grip than asphalt.
+    fn hazard_fraction(&self, table: &ArcLengthTable) -> f32 {
+        let params = table.uniform_params(TRACK_SAMPLE_SPACING);
+        let length = table.length();
+        let hazards = params
+            .iter()
+            .flat_map(|&t| {
+                let distance = table.distance_at_t(t);
+                [TrackSide::Left, TrackSide::Right]
+                    .map(|side| surface_at(&self.surfaces, distance, side, length))
+            })
+            .filter(|kind| kind.is_some_and(|kind| kind.grip() < 1.0))
+            .count();
+        hazards as f32 / (params.len() * 2) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use bevy::math::{Vec2, vec2};
+
+    use super::*;
+
+    fn circle(radius: f32, width: f32) -> RaceTrack {
+        let points = (0..12)
+            .map(|i| Vec2::from_angle(i as f32 * TAU / 12.0) * radius)
+            .collect::<Vec<_>>();
+        RaceTrack {
+            widths: vec![width; points.len()],
+            points,
+            closed: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn times_are_shown_in_minutes_seconds_and_tenths() {
+        assert_eq!(format_time(83.44), "1:23.4");
+        assert_eq!(format_time(5.0), "0:05.0");
+        assert_eq!(format_time(599.96), "10:00.0");
+    }
+
+    #[test]
+    fn a_straight_is_driven_flat_out_from_a_standing_start() {
+        let track = RaceTrack {
+            points: (0..=10).map(|i| vec2(i as f32 * 100.0, 0.0)).collect(),
+            widths: vec![DEFAULT_TRACK_WIDTH; 11],
+            closed: false,
+            ..Default::default()
+        };
+        let stats = track.stats().unwrap();
+        let car = REFERENCE_CAR;
+        // Up to top speed, then the rest of the way at it.
+        let speeding_up = car.top_speed / car.acceleration;
+        let speeding_up_distance = car.top_speed * speeding_up / 2.0;
+        let expected = speeding_up + (stats.length - speeding_up_distance) / car.top_speed;
+        assert!(
+            (stats.lap_time - expected).abs() < expected * 0.05,
+            "{stats:?}, expected {expected}"
+        );
+        assert_eq!(stats.corners, 0);
+        assert!((stats.longest_straight - stats.length).abs() < 1.0, "{stats:?}");
+    }
+
+    #[test]
+    fn tight_narrow_tracks_are_harder_than_wide_sweeping_ones() {
+        let easy = circle(400.0, 2.0 * DEFAULT_TRACK_WIDTH).stats().unwrap();
+        let hard = circle(70.0, MIN_TRACK_WIDTH).stats().unwrap();
+        assert!(easy.difficulty < hard.difficulty, "{easy:?} {hard:?}");
+        for stats in [easy, hard] {
+            assert!((0.0..=10.0).contains(&stats.difficulty), "{stats:?}");
+        }
+    }
+}
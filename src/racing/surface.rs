@@ -0,0 +1,181 @@
+//! Surface zones on a [`RaceTrack`]: stretches of gravel, ice, oil, mud or boost pads.
+//!
+//! A zone covers the road from one distance along the centerline to another, optionally on one
+//! side only. The road mesh colours each zone, and gameplay spawns a sensor per zone so a car's
+//! [`CurrentSurface`] changes its grip and top speed.
+
+use avian2d::prelude::CollidingEntities;
+use bevy::color::Color;
+use bevy::prelude::{Component, DetectChangesMut, Query, Reflect};
+use serde::{Deserialize, Serialize};
+
+use super::arc_length::distance_range_contains;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+#[serde(rename_all = "snake_case")]
+pub enum SurfaceKind {
+    Gravel,
+    Ice,
+    Oil,
+    Mud,
+    Boost,
+}
+
+impl SurfaceKind {
+    /// How well tyres hold on this surface, where asphalt is 1.
+    pub fn grip(self) -> f32 {
+        match self {
+            SurfaceKind::Gravel => 0.6,
+            SurfaceKind::Ice => 0.2,
+            SurfaceKind::Oil => 0.1,
+            SurfaceKind::Mud => 0.5,
+            SurfaceKind::Boost => 1.0,
+        }
+    }
+
+    /// The fraction of a car's top speed it can reach on this surface, where asphalt is 1.
+    pub fn top_speed(self) -> f32 {
+        match self {
+            SurfaceKind::Gravel => 0.7,
+            SurfaceKind::Ice => 1.0,
+            SurfaceKind::Oil => 1.0,
+            SurfaceKind::Mud => 0.5,
+            SurfaceKind::Boost => 1.5,
+        }
+    }
+
+    /// The colour the surface is drawn with on the road mesh.
+    pub fn color(self) -> Color {
+        match self {
+            SurfaceKind::Gravel => Color::srgb(0.65, 0.55, 0.4),
+            SurfaceKind::Ice => Color::srgb(0.75, 0.9, 1.0),
+            SurfaceKind::Oil => Color::srgb(0.1, 0.1, 0.15),
+            SurfaceKind::Mud => Color::srgb(0.4, 0.27, 0.15),
+            SurfaceKind::Boost => Color::srgb(1.0, 0.5, 0.1),
+        }
+    }
+}
+
+/// One half of the road, seen in the direction of travel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackSide {
+    Left,
+    Right,
+}
+
+/// A stretch of the road with a different surface.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+pub struct SurfaceZone {
+    pub kind: SurfaceKind,
+    /// The distance along the centerline where the zone begins.
+    pub start: f32,
+    /// The distance along the centerline where the zone ends. A zone ending before it begins runs
+    /// across the start of the curve.
+    pub end: f32,
+    /// The half of the road the zone covers, or `None` for the full width.
+    #[serde(default)]
+    pub side: Option<TrackSide>,
+}
+
+impl SurfaceZone {
+    /// Whether the zone covers `side` of the road at `distance`, on a centerline `length` long.
+    pub fn contains(&self, distance: f32, side: TrackSide, length: f32) -> bool {
+        self.side.is_none_or(|zone_side| zone_side == side)
+            && distance_range_contains(self.start, self.end, distance, length)
+    }
+}
+
+/// The surface on `side` of the road at `distance`. Zones later in the list are on top of earlier
+/// ones.
+pub fn surface_at(
+    zones: &[SurfaceZone],
+    distance: f32,
+    side: TrackSide,
+    length: f32,
+) -> Option<SurfaceKind> {
+    zones
+        .iter()
+        .rev()
+        .find(|zone| zone.contains(distance, side, length))
+        .map(|zone| zone.kind)
+}
+
+/// Marks the sensor covering a [`SurfaceZone`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SurfaceSensor {
+    pub kind: SurfaceKind,
+    /// The position of the zone in [`RaceTrack::surfaces`](super::RaceTrack::surfaces). As in
+    /// [`surface_at`], a zone is on top of every zone before it.
+    pub layer: usize,
+}
+
+/// The surface a car is driving on, or `None` for plain asphalt. Needs [`CollidingEntities`] on
+/// the same entity.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CurrentSurface(pub Option<SurfaceKind>);
+
+impl CurrentSurface {
+    pub fn grip(&self) -> f32 {
+        self.0.map_or(1.0, SurfaceKind::grip)
+    }
+
+    pub fn top_speed(&self) -> f32 {
+        self.0.map_or(1.0, SurfaceKind::top_speed)
+    }
+}
+
+/// Keeps [`CurrentSurface`] up to date from the surface sensors each car is touching. Where zones
+/// overlap under a car, the one on top wins, the same one [`surface_at`] picks and the road mesh is
+/// coloured with.
+pub fn update_current_surface(
+    mut cars: Query<(&CollidingEntities, &mut CurrentSurface)>,
+    sensors: Query<&SurfaceSensor>,
+) {
+    for (colliding, mut surface) in &mut cars {
+        let kind = sensors
+            .iter_many(colliding.iter())
+            .max_by_key(|sensor| sensor.layer)
+            .map(|sensor| sensor.kind);
+        surface.set_if_neq(CurrentSurface(kind));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::World;
+
+    use super::*;
+
+    #[test]
+    fn overlapping_zones_drive_like_they_look() {
+        let zones = [
+            SurfaceZone {
+                kind: SurfaceKind::Oil,
+                start: 100.0,
+                end: 300.0,
+                side: None,
+            },
+            SurfaceZone {
+                kind: SurfaceKind::Gravel,
+                start: 200.0,
+                end: 400.0,
+                side: Some(TrackSide::Left),
+            },
+        ];
+        let shown = surface_at(&zones, 250.0, TrackSide::Left, 1000.0);
+        assert_eq!(shown, Some(SurfaceKind::Gravel));
+
+        let mut world = World::new();
+        let sensors = zones
+            .iter()
+            .enumerate()
+            .map(|(layer, zone)| world.spawn(SurfaceSensor { kind: zone.kind, layer }).id());
+        let colliding = CollidingEntities(sensors.collect());
+        let car = world.spawn((colliding, CurrentSurface::default())).id();
+        world.run_system_once(update_current_surface).unwrap();
+
+        assert_eq!(world.get::<CurrentSurface>(car), Some(&CurrentSurface(shown)));
+    }
+}
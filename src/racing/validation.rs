@@ -0,0 +1,310 @@
+//! Geometry checks for a [`RaceTrack`].
+//!
+//! A track can be drawn in the editor that cannot be raced: the curve may cross itself without a
+//! bridge, corners may be tighter than the road is wide so the inner edge folds over, or points may
+//! sit on top of each other. [`RaceTrack::validate`] reports each of these with a location to
+//! highlight.
+
+use std::fmt;
+
+use bevy::math::Vec2;
+
+use super::arc_length::{ArcLengthTable, TRACK_SAMPLE_SPACING};
+use super::{MIN_TRACK_WIDTH, RaceTrack};
+
+/// Fewer than two points cannot form a curve at all.
+pub const MIN_CONTROL_POINTS: usize = 2;
+
+/// Control points closer together than this are considered duplicates.
+pub const DUPLICATE_POINT_DISTANCE: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackIssueKind {
+    TooFewPoints { count: usize },
+    DuplicatePoints { first: usize, second: usize },
+    /// The track crosses itself, and not with exactly one of the two pieces on a bridge.
+    SelfIntersection,
+    /// The radius of a corner is smaller than half the road, so the inner edge folds over.
+    CornerTooTight { radius: f32, half_width: f32 },
+}
+
+/// A problem with the geometry of a track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackIssue {
+    pub kind: TrackIssueKind,
+    /// Where on the track the problem is.
+    pub location: Vec2,
+}
+
+impl fmt::Display for TrackIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Vec2 { x, y } = self.location;
+        match self.kind {
+            TrackIssueKind::TooFewPoints { count } => write!(
+                f,
+                "Only {count} control points, at least {MIN_CONTROL_POINTS} are needed"
+            ),
+            TrackIssueKind::DuplicatePoints { first, second } => write!(
+                f,
+                "Control points {first} and {second} are on top of each other at ({x:.0}, {y:.0})"
+            ),
+            TrackIssueKind::SelfIntersection => write!(
+                f,
+                "The track crosses itself at ({x:.0}, {y:.0}) without a bridge"
+            ),
+            TrackIssueKind::CornerTooTight { radius, half_width } => write!(
+                f,
+                "Corner at ({x:.0}, {y:.0}) has radius {radius:.0}, tighter than half the road ({half_width:.0})"
+            ),
+        }
+    }
+}
+
+impl RaceTrack {
+    /// Checks the geometry of the track. An empty list means the track is fine to race on.
+    pub fn validate(&self) -> Vec<TrackIssue> {
+        if self.points.len() < MIN_CONTROL_POINTS {
+            return vec![TrackIssue {
+                kind: TrackIssueKind::TooFewPoints {
+                    count: self.points.len(),
+                },
+                location: self.points.first().copied().unwrap_or_default(),
+            }];
+        }
+
+        let mut issues = self.duplicate_points();
+        if let Some(table) = self.arc_length_table() {
+            let length = table.length();
+            issues.extend(
+                self_intersections(&table)
+                    .into_iter()
+                    .filter(|crossing| {
+                        let [first, second] = crossing.distances;
+                        self.on_bridge(first, length) == self.on_bridge(second, length)
+                    })
+                    .map(|crossing| TrackIssue {
+                        kind: TrackIssueKind::SelfIntersection,
+                        location: crossing.location,
+                    }),
+            );
+            issues.extend(self.tight_corners(&table));
+        }
+        issues
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_empty()
+    }
+
+    fn duplicate_points(&self) -> Vec<TrackIssue> {
+        let mut issues = Vec::new();
+        for (first, a) in self.points.iter().enumerate() {
+            for (second, b) in self.points.iter().enumerate().skip(first + 1) {
+                if a.distance(*b) < DUPLICATE_POINT_DISTANCE {
+                    issues.push(TrackIssue {
+                        kind: TrackIssueKind::DuplicatePoints { first, second },
+                        location: *a,
+                    });
+                }
+            }
+        }
+        issues
+    }
+
+    /// Finds stretches of the track where the corner radius is smaller than half the road, and
+    /// reports the tightest point of each stretch.
+    fn tight_corners(&self, table: &ArcLengthTable) -> Vec<TrackIssue> {
+        let Some(width_curve) = self.form_width_curve() else {
+            return Vec::new();
+        };
+
+        let mut issues = Vec::new();
+        let mut current: Option<TrackIssue> = None;
+        for t in table.uniform_params(TRACK_SAMPLE_SPACING / 2.0) {
+            let radius = 1.0 / table.curvature_at_t(t).abs();
+            let half_width = width_curve.position(t).max(MIN_TRACK_WIDTH) / 2.0;
+            if radius >= half_width {
+                issues.extend(current.take());
+                continue;
+            }
+
+            let issue = TrackIssue {
+                kind: TrackIssueKind::CornerTooTight { radius, half_width },
+                location: table.curve().position(t),
+            };
+            current = match current {
+                Some(TrackIssue {
+                    kind: TrackIssueKind::CornerTooTight { radius: tightest, .. },
+                    ..
+                }) if tightest <= radius => current,
+                _ => Some(issue),
+            };
+        }
+        issues.extend(current);
+        issues
+    }
+}
+
+/// A point where two pieces of the centerline cross.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossing {
+    pub location: Vec2,
+    /// The distance along the centerline of each of the two pieces at the crossing.
+    pub distances: [f32; 2],
+}
+
+/// Every point where two non-adjacent pieces of the centerline cross.
+pub fn self_intersections(table: &ArcLengthTable) -> Vec<Crossing> {
+    let samples = table.uniform_positions(TRACK_SAMPLE_SPACING);
+    let count = samples.len();
+    let segment = |i: usize| (samples[i], samples[(i + 1) % count]);
+    // An open centerline has no segment joining the last sample back to the first.
+    let segment_count = if table.is_closed() {
+        count
+    } else {
+        count.saturating_sub(1)
+    };
+    let step = table.length() / segment_count.max(1) as f32;
+    let distance_along = |i: usize, point: Vec2| {
+        let (start, end) = segment(i);
+        let fraction = start.distance(point) / start.distance(end).max(f32::EPSILON);
+        (i as f32 + fraction) * step
+    };
+
+    let mut intersections: Vec<Crossing> = Vec::new();
+    for i in 0..segment_count {
+        // Skip the neighbours, which always share an end with this segment.
+        for j in (i + 2..segment_count).filter(|&j| (j + 1) % count != i) {
+            let Some(intersection) = segment_intersection(segment(i), segment(j)) else {
+                continue;
+            };
+            // A crossing right on a sample can be found twice, once for each segment it touches.
+            if intersections
+                .iter()
+                .all(|other| other.location.distance(intersection) > TRACK_SAMPLE_SPACING)
+            {
+                intersections.push(Crossing {
+                    location: intersection,
+                    distances: [distance_along(i, intersection), distance_along(j, intersection)],
+                });
+            }
+        }
+    }
+    intersections
+}
+
+/// The point where two line segments cross, if they do.
+pub fn segment_intersection((a0, a1): (Vec2, Vec2), (b0, b1): (Vec2, Vec2)) -> Option<Vec2> {
+    let a = a1 - a0;
+    let b = b1 - b0;
+    let denominator = a.perp_dot(b);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let offset = b0 - a0;
+    let s = offset.perp_dot(b) / denominator;
+    let t = offset.perp_dot(a) / denominator;
+    ((0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t)).then(|| a0 + a * s)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{PI, TAU};
+
+    use bevy::math::vec2;
+
+    use super::super::bridge::Bridge;
+    use super::*;
+
+    /// A closed track through `points`, with a road narrow enough for any of them.
+    fn track(points: Vec<Vec2>) -> RaceTrack {
+        RaceTrack {
+            widths: vec![40.0; points.len()],
+            points,
+            closed: true,
+            ..Default::default()
+        }
+    }
+
+    fn circle() -> RaceTrack {
+        track(
+            (0..12)
+                .map(|i| Vec2::from_angle(i as f32 * TAU / 12.0) * 300.0)
+                .collect(),
+        )
+    }
+
+    /// A figure eight, crossing itself at the origin halfway between points 3 and 4.
+    fn figure_eight() -> RaceTrack {
+        track(
+            (0..8)
+                .map(|k| {
+                    let angle = PI / 8.0 + k as f32 * PI / 4.0;
+                    vec2(400.0 * angle.sin(), 200.0 * (2.0 * angle).sin())
+                })
+                .collect(),
+        )
+    }
+
+    fn kinds(track: &RaceTrack) -> Vec<TrackIssueKind> {
+        track.validate().into_iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn a_round_track_is_valid() {
+        assert_eq!(circle().validate(), Vec::new());
+    }
+
+    #[test]
+    fn a_single_point_is_too_few() {
+        let track = track(vec![vec2(10.0, 20.0)]);
+        assert_eq!(kinds(&track), [TrackIssueKind::TooFewPoints { count: 1 }]);
+    }
+
+    #[test]
+    fn points_on_top_of_each_other_are_duplicates() {
+        let mut track = circle();
+        let point = track.points[0] + vec2(0.5, 0.0);
+        track.points.insert(1, point);
+        track.widths.insert(1, 40.0);
+        assert!(
+            kinds(&track).contains(&TrackIssueKind::DuplicatePoints { first: 0, second: 1 }),
+            "{:?}",
+            track.validate()
+        );
+    }
+
+    #[test]
+    fn a_crossing_needs_a_bridge() {
+        let mut track = figure_eight();
+        let crossings = track
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.kind == TrackIssueKind::SelfIntersection)
+            .collect::<Vec<_>>();
+        assert_eq!(crossings.len(), 1, "{crossings:?}");
+        assert!(crossings[0].location.length() < 5.0, "{crossings:?}");
+
+        let crossing = track.arc_length_table().unwrap().distance_at_t(3.5);
+        track.bridges.push(Bridge {
+            start: crossing - 100.0,
+            end: crossing + 100.0,
+        });
+        assert!(!kinds(&track).contains(&TrackIssueKind::SelfIntersection));
+    }
+
+    #[test]
+    fn a_corner_tighter_than_the_road_is_reported() {
+        let mut track = circle();
+        track.widths = vec![700.0; track.points.len()];
+        let issues = kinds(&track);
+        assert!(!issues.is_empty());
+        assert!(
+            issues.iter().all(|kind| matches!(
+                kind,
+                TrackIssueKind::CornerTooTight { radius, half_width } if radius < half_width
+            )),
+            "{issues:?}"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! A screen listing the cups to pick one for a championship, or to carry on with the one under
+//! way.
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*, ui::Val::*};
+
+use crate::{
+    demo::level::{LevelAssets, PLAYER_ENTRANT},
+    racing::{
+        cup::{Championship, Cup, CupsAsset},
+        library::TrackLibrary,
+    },
+    screens::Screen,
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::CupSelect), spawn_cup_select_screen);
+    app.add_systems(
+        Update,
+        go_back.run_if(in_state(Screen::CupSelect).and(input_just_pressed(KeyCode::Escape))),
+    );
+}
+
+/// Only entered once the level assets are loaded, so the cups are there to list.
+fn spawn_cup_select_screen(
+    mut commands: Commands,
+    level_assets: Res<LevelAssets>,
+    cup_assets: Res<Assets<CupsAsset>>,
+    championship: Option<Res<Championship>>,
+) {
+    let cups = cup_assets
+        .get(&level_assets.cups)
+        .map(|cups| cups.cups.clone())
+        .unwrap_or_default();
+    let under_way = championship
+        .filter(|championship| !championship.is_finished())
+        .map(|championship| {
+            format!(
+                "{}: race {} of {}",
+                championship.cup.name,
+                championship.race_index + 1,
+                championship.cup.races.len()
+            )
+        });
+
+    let rows = cups
+        .into_iter()
+        .map(|cup| {
+            let summary = format!("{} races", cup.races.len());
+            (
+                Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Px(20.0),
+                    ..default()
+                },
+                children![
+                    widget::button(
+                        cup.name.clone(),
+                        move |_: Trigger<Pointer<Click>>,
+                              mut commands: Commands,
+                              mut next_screen: ResMut<NextState<Screen>>| {
+                            commands.insert_resource(start_championship(cup.clone()));
+                            next_screen.set(Screen::Gameplay);
+                        },
+                    ),
+                    widget::label(summary),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+
+    commands
+        .spawn((
+            widget::ui_root("Cup Select Screen"),
+            StateScoped(Screen::CupSelect),
+        ))
+        .with_children(|parent| {
+            parent.spawn(widget::header("Cups"));
+            if let Some(under_way) = under_way {
+                parent.spawn(widget::label(under_way));
+                parent.spawn(widget::button("Continue", continue_championship));
+            }
+            if rows.is_empty() {
+                parent.spawn(widget::label("No cups found"));
+            }
+            for row in rows {
+                parent.spawn(row);
+            }
+            parent.spawn(widget::button("Back", go_back_on_click));
+        });
+}
+
+/// Starts `cup` from its first race, with its races put in order of difficulty if it asks for it.
+fn start_championship(mut cup: Cup) -> Championship {
+    if cup.order_by_difficulty {
+        let library = TrackLibrary::scan();
+        for err in &library.errors {
+            warn!("{err}");
+        }
+        cup.sort_by_difficulty(&library);
+    }
+    Championship::new(cup, [PLAYER_ENTRANT.to_string()])
+}
+
+fn continue_championship(_: Trigger<Pointer<Click>>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Gameplay);
+}
+
+fn go_back_on_click(_: Trigger<Pointer<Click>>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
+
+fn go_back(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
@@ -1,38 +1,83 @@
 //! The screen state for the main gameplay.
 
-use crate::screens::Screen;
+use crate::screens::{Screen, track_error::TrackLoadError};
 use bevy::{
     gizmos::gizmos::Gizmos,
-    input::{mouse::MouseButtonInput, ButtonState},
-    math::{cubic_splines::*, vec2},
+    input::{
+        keyboard::{Key, KeyboardInput},
+        mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseButtonInput, MouseScrollUnit},
+        ButtonState,
+    },
+    math::vec2,
     prelude::*,
 };
-use std::fs;
-use bevy::asset::RenderAssetUsages;
-use bevy::color::palettes::basic::GRAY;
-use bevy::render::mesh::{Indices, PrimitiveTopology};
-use crate::racing::{ControlPoints, Curves, TracksAsset, TrackPart, RESOLUTION};
+use std::path::{Path, PathBuf};
+use bevy::color::palettes::basic::{GRAY, SILVER};
+use crate::racing::library::{self, TrackLibrary};
+use crate::racing::gates::RaceMode;
+use crate::racing::arc_length::{PointInsertion, TRACK_SAMPLE_SPACING};
+use crate::racing::bridge::BRIDGE_Z;
+use crate::racing::corners::CornerSeverity;
+use crate::racing::racing_line::{RacingLineCache, ShowRacingLine};
+use crate::racing::props::{PROP_PICK_RADIUS, PropKind, TrackProp};
+use crate::racing::mesh::{TrackMeshSettings, build_bridge_mesh, build_track_mesh};
+use crate::racing::generator::{GeneratorSettings, generate_track};
+use crate::racing::history::{EditCommand, EditHistory};
+use crate::racing::validation::TrackIssue;
+use crate::racing::{ControlPoints, Curves, RaceTrack, TracksAsset, TrackPart, DEFAULT_TRACK_WIDTH, MIN_TRACK_WIDTH, POINT_PICK_RADIUS};
+
+/// How much the width of a control point changes per key press.
+const WIDTH_STEP: f32 = 5.0;
+
+/// The radius of the circles control points are drawn as.
+const CONTROL_POINT_RADIUS: f32 = 10.0;
 
 pub(super) fn plugin(app: &mut App) {
     app
+        .init_resource::<RacingLineCache>()
+        .init_resource::<ShowRacingLine>()
         .add_systems(OnEnter(Screen::Editor), setup_editor)
+        .add_systems(OnExit(Screen::Editor), reset_camera)
         .add_systems(
             Update,
             (
-                handle_keypress,
-                handle_mouse_move,
-                handle_mouse_press,
-                draw_edit_move,
-                update_curve,
-                draw_curve,
-                draw_control_points,
+                (
+                    handle_text_prompt,
+                    handle_keypress,
+                    handle_mouse_move,
+                    control_camera,
+                    handle_grid_keys,
+                    update_hovered_point,
+                    update_insert_preview,
+                    handle_mouse_press,
+                    handle_prop_input,
+                )
+                    .chain(),
+                (
+                    draw_grid,
+                    draw_edit_move,
+                    draw_alignment_guides,
+                    draw_insert_preview,
+                    update_curve,
+                    draw_curve,
+                    draw_control_points,
+                    draw_track_markers,
+                    draw_props,
+                    draw_racing_line,
+                    draw_corners,
+                    validate_track,
+                    update_stats_text,
+                    draw_track_issues,
+                    update_files_text,
+                )
+                    .chain(),
             )
                 .chain()
                 .run_if(in_state(Screen::Editor)),
         );
 }
 
-pub fn setup_editor(mut commands: Commands) {
+pub fn setup_editor(mut commands: Commands, mut next_screen: ResMut<NextState<Screen>>) {
     // Initialize the modes with their defaults:
     
     // Starting data for [`ControlPoints`]:
@@ -41,36 +86,84 @@ pub fn setup_editor(mut commands: Commands) {
         vec2(-500., -150.)
     ];
     
-    let mut tracks_asset = load_from_file("assets/race.tracks");
+    let files = EditorFiles::scan();
+    if let Some(error) = files.load_error() {
+        // Leave for the error screen right away, so no file that failed to load is edited as if
+        // it were empty. The editor systems keep running on what did load until the transition.
+        commands.insert_resource(error);
+        next_screen.set(Screen::TrackError);
+    }
+    let mut tracks_asset = files
+        .library
+        .files
+        .first()
+        .map(|file| file.tracks.clone())
+        .unwrap_or_default();
     let start_track = tracks_asset.get_next_track();
     let default_control_data = match start_track {
         Some(track) => ControlPoints {
             points: track.points.clone(),
+            widths: track.point_widths(),
             selected: None,
         },
         None => ControlPoints {
+            widths: vec![DEFAULT_TRACK_WIDTH; default_points.len()],
             points: default_points,
             selected: None,
         },
     };
-    let curve = form_curve(&default_control_data);
+    let curve = editing_track(&default_control_data, &tracks_asset).form_curve();
     commands.insert_resource(curve);
     commands.insert_resource(default_control_data);
     commands.insert_resource(tracks_asset);
+    commands.insert_resource(files);
     // Mouse tracking information:
     commands.insert_resource(MousePosition::default());
     commands.insert_resource(MouseEditMove::default());
-    commands.insert_resource(MouseMoveMove::default());
+    commands.insert_resource(PointDrag::default());
+    commands.insert_resource(HoveredPoint::default());
+    commands.insert_resource(InsertPreview::default());
+    commands.insert_resource(EditorView::default());
+    commands.insert_resource(EditorGrid::default());
+    commands.insert_resource(TrackIssues::default());
+    commands.insert_resource(PropEditing::default());
+    commands.insert_resource(TextPrompt::default());
+    commands.insert_resource(EditHistory::default());
     
 
     // The instructions and modes are rendered on the left-hand side in a column.
     let instructions_text = "Click and drag to add control points\n\
+        Click a control point to select it, drag it to move it\n\
+        Click on the road to insert a control point there\n\
         R: Remove the selected control point\n\
         Left-Right-Arrows: Change selected control point\n\
+        +/-: Widen or narrow the track at the selected control point\n\
+        F: Move the start/finish line to the selected control point\n\
+        E: Move the finish line of a point-to-point track to the selected control point\n\
+        O: Switch between a loop and a point-to-point track\n\
+        B: Add or remove a bridge around the selected control point\n\
+        C: Add or remove a checkpoint at the selected control point\n\
+        G: Replace the current track with one generated from a seed\n\
+        P: Switch between editing control points and placing props\n\
+        I: Show or hide the racing line\n\
+        Middle-drag or Space+drag: Pan the view\n\
+        Scroll: Zoom in or out around the cursor\n\
+        Home: Fit the whole track in the view\n\
+        Q: Show the grid and snap points to it, [ and ]: change the grid spacing\n\
+        M: Mirror new points about a vertical or horizontal axis, or stop mirroring\n\
+        Shift+M: Move the mirror axis to the cursor\n\
+        In prop mode: click to place, 1-4 to choose the prop, right-drag to move, X to delete\n\
+        Ctrl+C: Copy the code of the current track for sharing\n\
+        Ctrl+V: Import a track from a code\n\
+        Ctrl+Z/Ctrl+Shift+Z: Undo or redo the last change to the current track\n\
+        F2: Rename the current track\n\
+        F3: Name the corner at the selected point\n\
         Up-Down-Arrows: Change current track\n\
+        Page Up/Page Down: Open the previous or next tracks file\n\
+        T: Choose the file the current track is saved to\n\
         N: New Track\n\
-        S: Save racing.tracks\n\
-        L: Load racing.tracks";
+        S: Save the current track\n\
+        L: Reload the open file";
     let style = TextFont::default();
 
     commands
@@ -84,6 +177,25 @@ pub fn setup_editor(mut commands: Commands) {
         })
         .with_children(|parent| {
             parent.spawn((Text::new(instructions_text), style.clone()));
+            parent.spawn((FilesText, Text::new(""), style.clone()));
+            parent.spawn((StatsText, Text::new(""), style.clone()));
+            parent.spawn((
+                PromptText,
+                Text::new(""),
+                style.clone(),
+                TextLayout::new_with_linebreak(LineBreak::AnyCharacter),
+                Node {
+                    max_width: Val::Px(600.0),
+                    ..default()
+                },
+                TextColor(Color::srgb(0.4, 0.8, 1.0)),
+            ));
+            parent.spawn((
+                IssuesText,
+                Text::new(""),
+                style.clone(),
+                TextColor(Color::srgb(1.0, 0.3, 0.3)),
+            ));
         });
 }
 
@@ -97,6 +209,7 @@ pub fn setup_editor(mut commands: Commands) {
 /// [control points]: ControlPoints
 fn update_curve(
     control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
     mut commands: Commands,
     mut curve: ResMut<Curves>,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -111,62 +224,35 @@ fn update_curve(
         commands.entity(mesh).despawn();
     }
 
+    let track = editing_track(&control_points, &tracks_asset);
+    *curve = track.form_curve();
+    let settings = TrackMeshSettings {
+        vertex_color: Some(GRAY.into()),
+        ..default()
+    };
+    let Some(edges) = track.edges(settings.spacing) else {
+        return;
+    };
+    let material = materials.add(Color::WHITE);
 
-    let points =
-        control_points.points.iter().copied();
-    let spline = CubicCardinalSpline::new_catmull_rom(points);
-    
-    *curve = Curves(spline.to_curve_cyclic().ok());
-    let track_curve = curve.0.as_ref().unwrap();
-    let resolution = RESOLUTION * track_curve.segments().len();
-    let track_curve = track_curve.iter_positions(resolution)
-        .collect::<Vec<_>>();
-    
-    let bounds = compute_bounds(&track_curve);
-    
-    for (i, (p0, p1)) in bounds.iter().enumerate() {
-        let mut mesh = Mesh::new(
-            PrimitiveTopology::TriangleList,
-            RenderAssetUsages::RENDER_WORLD,
-        );
-        /*
-        our triangles are, maybe?
-        p2---p3
-        p0  
-             p3
-        p0---p1
-        
-        
-         */
-        let (p2, p3) = if i == bounds.len() - 1 {&bounds[0]} else {&bounds[i + 1]};
-        
-        let vertices = vec![
-            [p0.x, p0.y, 0.0], //0
-            [p1.x, p1.y, 0.0], //1
-            [p2.x, p2.y, 0.0], //2
-            [p3.x, p3.y, 0.0], //3
-        ];
-        let color = LinearRgba::from(GRAY);
-        let colors = vertices.iter().map(|_| 
-        [color.red, color.green, color.blue, color.alpha]
-        ).collect::<Vec<_>>();
-        
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
-
-        let indices = vec![
-            0, 2, 3,
-            0,1,3
-        ];
-        mesh.insert_indices(Indices::U32(indices));
-
+    commands.spawn((
+        TrackPart,
+        StateScoped(Screen::Editor),
+        Mesh2d(meshes.add(build_track_mesh(&edges, &track.surfaces, &settings))),
+        MeshMaterial2d(material.clone()),
+    ));
+    let bridge_settings = TrackMeshSettings {
+        vertex_color: Some(SILVER.into()),
+        ..settings
+    };
+    for bridge in &track.bridges {
         commands.spawn((
-                           TrackPart,
-                           Mesh2d(meshes.add(mesh)),
-                           MeshMaterial2d(materials.add(Color::from(GRAY)))
-                           )
-        );
-
+            TrackPart,
+            StateScoped(Screen::Editor),
+            Mesh2d(meshes.add(build_bridge_mesh(&edges, bridge, &bridge_settings))),
+            MeshMaterial2d(material.clone()),
+            Transform::from_xyz(0.0, 0.0, BRIDGE_Z),
+        ));
     }
 }
 
@@ -185,64 +271,561 @@ fn draw_curve(curve: Res<Curves>, mut gizmos: Gizmos) {
     );
 }
 
-/// This system uses gizmos to draw the current [control points] as circles, displaying their
-/// tangent vectors as arrows in the case of a Hermite spline.
+/// This system uses gizmos to draw the current [control points] as circles, with a ring around
+/// the one under the cursor.
 ///
 /// [control points]: ControlPoints
 fn draw_control_points(
     control_points: Res<ControlPoints>,
+    hovered: Res<HoveredPoint>,
     mut gizmos: Gizmos,
 ) {
     for (i, point) in control_points.points.iter().enumerate() { 
         if Some(i) == control_points.selected {
-            gizmos.circle_2d(*point, 10.0, Color::srgb(1.0, 0.0, 0.0));
+            gizmos.circle_2d(*point, CONTROL_POINT_RADIUS, Color::srgb(1.0, 0.0, 0.0));
            
         } else {
-            gizmos.circle_2d(*point, 10.0, Color::srgb(0.0, 1.0, 0.0));
+            gizmos.circle_2d(*point, CONTROL_POINT_RADIUS, Color::srgb(0.0, 1.0, 0.0));
+        }
+        if Some(i) == hovered.0 {
+            gizmos.circle_2d(*point, POINT_PICK_RADIUS, Color::srgb(1.0, 1.0, 0.0));
         }
     }
 }
 
-/// Helper function for generating a [`Curves`] from [control points] and selected modes.
+/// This system uses gizmos to draw the start/finish line, the checkpoint gates and the starting
+/// grid of the track being edited.
+fn draw_track_markers(
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    mut gizmos: Gizmos,
+) {
+    let track = editing_track(&control_points, &tracks_asset);
+
+    for gate in track.checkpoint_gates() {
+        let (left, right) = gate.edges();
+        gizmos.line_2d(left, right, Color::srgb(1.0, 0.8, 0.0));
+    }
+    if let Some(gate) = track.start_line_gate() {
+        let (left, right) = gate.edges();
+        gizmos.line_2d(left, right, Color::srgb(1.0, 1.0, 1.0));
+    }
+    if let (RaceMode::Sprint, Some(gate)) = (track.race_mode(), track.finish_line_gate()) {
+        let (left, right) = gate.edges();
+        gizmos.line_2d(left, right, Color::srgb(1.0, 0.2, 0.2));
+    }
+    for slot in track.starting_grid() {
+        let isometry = Isometry2d::new(slot.position, Rot2::radians(slot.direction.to_angle()));
+        gizmos.rect_2d(isometry, vec2(16.0, 8.0), Color::srgb(0.3, 0.6, 1.0));
+    }
+}
+
+/// The current track, with the [control points] being edited applied to it.
 ///
 /// [control points]: ControlPoints
-fn form_curve(
-    control_points: &ControlPoints
-) -> Curves {
-    let points =
-        control_points.points.iter().copied();
-    let spline = CubicCardinalSpline::new_catmull_rom(points);
-    
-    Curves(spline.to_curve_cyclic().ok())
-}
-
-pub fn compute_bounds(
-    control_points: &[Vec2],
-) -> Vec<(Vec2,Vec2)> {
-    let mut normals = Vec::new();
-    let tension = 0.5;
-
-    for i in 0..control_points.len() {
-        let tangent = if i == 0 {
-            // Forward difference at start
-            (control_points[i + 1] - control_points[i]) * tension * 2.0
-        } else if i == control_points.len() - 1 {
-            // Backward difference at end
-            (control_points[i] - control_points[i - 1]) * tension * 2.0
-        } else {
-            // Central difference for internal points
-            (control_points[i + 1] - control_points[i - 1]) * tension
+fn editing_track(
+    control_points: &ControlPoints,
+    tracks_asset: &TracksAsset,
+) -> RaceTrack {
+    let mut track = tracks_asset.get_current_track().cloned().unwrap_or_default();
+    track.points = control_points.points.clone();
+    track.widths = control_points.widths.clone();
+    track
+}
+
+// -----------------------------------
+// File-related Resources and Systems
+// -----------------------------------
+
+/// The tracks files the editor can open and save to.
+#[derive(Resource)]
+struct EditorFiles {
+    library: TrackLibrary,
+    /// The file being edited. Its tracks are in the [`TracksAsset`] resource.
+    open: PathBuf,
+    /// The file the current track is saved to. Saving to the open file saves all of its tracks.
+    target: PathBuf,
+}
+
+impl EditorFiles {
+    /// Scans the track library and opens its first file, or the default user file if it is empty.
+    fn scan() -> Self {
+        let library = TrackLibrary::scan();
+        for err in &library.errors {
+            warn!("{err}");
+        }
+        let open = library
+            .save_targets()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| {
+                PathBuf::from(library::BUNDLED_TRACKS_DIR).join(library::DEFAULT_USER_FILE)
+            });
+        Self {
+            library,
+            target: open.clone(),
+            open,
+        }
+    }
+
+    /// Why the library could not read some of its files, for the error screen.
+    fn load_error(&self) -> Option<TrackLoadError> {
+        let first = self.library.errors.first()?;
+        Some(TrackLoadError {
+            source: first.path().display().to_string(),
+            message: self
+                .library
+                .errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+    }
+
+    /// Opens the file `step` files after the open one, wrapping around, and returns its tracks.
+    fn open_next(&mut self, step: isize) -> Option<TracksAsset> {
+        let count = self.library.files.len();
+        if count == 0 {
+            return None;
+        }
+        let index = self
+            .library
+            .file_index(&self.open)
+            .map_or(0, |index| (index as isize + step).rem_euclid(count as isize) as usize);
+        let file = &self.library.files[index];
+        self.open = file.path.clone();
+        self.target = file.path.clone();
+        Some(file.tracks.clone())
+    }
+
+    /// Moves the save target on to the next file it can be saved to.
+    fn next_target(&mut self) {
+        let targets = self.library.save_targets();
+        let index = targets
+            .iter()
+            .position(|target| *target == self.target)
+            .map_or(0, |index| (index + 1) % targets.len());
+        if let Some(target) = targets.get(index) {
+            self.target = target.clone();
+        }
+    }
+}
+
+/// Marks the text showing the open file and where saves go.
+#[derive(Component)]
+struct FilesText;
+
+/// This system keeps the [`FilesText`] up to date with the [`EditorFiles`].
+fn update_files_text(files: Res<EditorFiles>, mut text: Single<&mut Text, With<FilesText>>) {
+    if !files.is_changed() {
+        return;
+    }
+
+    let describe = |path: &PathBuf| {
+        let source = files
+            .library
+            .file_index(path)
+            .map_or("new", |index| files.library.files[index].source.name());
+        format!("{} ({source})", path.display())
+    };
+    text.0 = format!(
+        "Editing: {}\nSaving to: {}\n{} tracks in {} files",
+        describe(&files.open),
+        describe(&files.target),
+        files.library.catalogue().count(),
+        files.library.files.len(),
+    );
+    if !files.library.errors.is_empty() {
+        text.0 += &format!("\n{} files could not be read", files.library.errors.len());
+    }
+}
+
+// -----------------------------------
+// Text Prompt Resources and Systems
+// -----------------------------------
+
+/// What the text typed into the [`TextPrompt`] is for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PromptKind {
+    /// A share code to import as a new track.
+    ShareCode,
+    /// A new name for the current track.
+    TrackName,
+    /// A name for the corner covering `distance` along the centerline.
+    CornerName { distance: f32 },
+    /// A seed to generate a track from, replacing the current one.
+    Seed,
+}
+
+/// The field share codes, track names, corner names and seeds are typed into. While it is open, keys go into the
+/// field instead of editing the track.
+#[derive(Clone, Default, Resource)]
+struct TextPrompt {
+    /// What the field is open for, or `None` while it is closed.
+    kind: Option<PromptKind>,
+    text: String,
+    /// Why the last attempt to use the text failed.
+    error: Option<String>,
+}
+
+impl TextPrompt {
+    fn open(kind: PromptKind, text: String) -> Self {
+        Self {
+            kind: Some(kind),
+            text,
+            error: None,
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.kind.is_some()
+    }
+}
+
+/// Marks the text showing the [`TextPrompt`].
+#[derive(Component)]
+struct PromptText;
+
+/// This system copies the code of the current track with Ctrl+C, opens the prompt for a share
+/// code with Ctrl+V, for a new track name with F2, for the name of the corner at the selected
+/// point with F3 and for a seed to generate a track from with G. While the prompt is open, typed and pasted text goes into it, Enter uses the
+/// text and Escape closes it.
+fn handle_text_prompt(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut prompt: ResMut<TextPrompt>,
+    mut history: ResMut<EditHistory>,
+    mut control_points: ResMut<ControlPoints>,
+    mut tracks_asset: ResMut<TracksAsset>,
+    mut text: Single<&mut Text, With<PromptText>>,
+) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let paste = ctrl && keyboard.just_pressed(KeyCode::KeyV);
+
+    if let Some(kind) = prompt.kind {
+        if paste {
+            prompt.text += &paste_from_clipboard();
+        }
+        for event in key_events.read() {
+            if event.state != ButtonState::Pressed {
+                continue;
+            }
+            match &event.logical_key {
+                Key::Enter => {
+                    let result = match kind {
+                        PromptKind::ShareCode => import_track(
+                            &prompt.text,
+                            &mut history,
+                            &mut control_points,
+                            &mut tracks_asset,
+                        ),
+                        PromptKind::TrackName => rename_track(
+                            &prompt.text,
+                            &mut history,
+                            &mut control_points,
+                            &mut tracks_asset,
+                        ),
+                        PromptKind::CornerName { distance } => {
+                            name_corner(
+                                distance,
+                                &prompt.text,
+                                &mut history,
+                                &mut control_points,
+                                &mut tracks_asset,
+                            );
+                            Ok(())
+                        }
+                        PromptKind::Seed => generate_from_seed(
+                            &prompt.text,
+                            &mut history,
+                            &mut control_points,
+                            &mut tracks_asset,
+                        ),
+                    };
+                    match result {
+                        Ok(()) => *prompt = TextPrompt::default(),
+                        Err(err) => prompt.error = Some(err),
+                    }
+                }
+                Key::Escape => *prompt = TextPrompt::default(),
+                Key::Backspace => {
+                    prompt.text.pop();
+                }
+                Key::Character(characters) if !ctrl => prompt.text.push_str(characters),
+                _ => {}
+            }
+        }
+    } else {
+        key_events.clear();
+        if ctrl && keyboard.just_pressed(KeyCode::KeyC) {
+            match editing_track(&control_points, &tracks_asset).share_code() {
+                Ok(code) => {
+                    info!("Track code: {code}");
+                    copy_to_clipboard(code);
+                }
+                Err(err) => error!("{err}"),
+            }
+        }
+        if paste {
+            *prompt = TextPrompt::open(PromptKind::ShareCode, paste_from_clipboard());
+        }
+        if keyboard.just_pressed(KeyCode::F2) {
+            let name = editing_track(&control_points, &tracks_asset).track_name;
+            *prompt = TextPrompt::open(PromptKind::TrackName, name);
+        }
+        if keyboard.just_pressed(KeyCode::F3) {
+            let track = editing_track(&control_points, &tracks_asset);
+            let corner = control_points
+                .selected
+                .zip(track.arc_length_table())
+                .and_then(|(selected, table)| {
+                    let corner = track.corner_at(table.distance_at_t(selected as f32))?;
+                    Some((corner, table.length()))
+                });
+            match corner {
+                Some((corner, length)) => {
+                    // Start from the corner's own name, if it has one.
+                    let name = track.corner_name(&corner, length);
+                    let name = if name == corner.default_name() { String::new() } else { name };
+                    let kind = PromptKind::CornerName {
+                        distance: corner.apex,
+                    };
+                    *prompt = TextPrompt::open(kind, name);
+                }
+                None => info!("There is no corner at the selected point"),
+            }
+        }
+        if keyboard.just_pressed(KeyCode::KeyG) && !ctrl {
+            // Offer a fresh seed, which can be typed over to replay a shared one.
+            let seed = rand::random::<u32>().to_string();
+            *prompt = TextPrompt::open(PromptKind::Seed, seed);
+        }
+    }
+
+    if prompt.is_changed() {
+        let title = match prompt.kind {
+            None => {
+                text.0 = String::new();
+                return;
+            }
+            Some(PromptKind::ShareCode) => {
+                "Paste a track code (Ctrl+V), Enter to import, Escape to cancel:"
+            }
+            Some(PromptKind::TrackName) => "Type a new name, Enter to rename, Escape to cancel:",
+            Some(PromptKind::CornerName { .. }) => {
+                "Type a corner name, or nothing to number it, Enter to rename, Escape to cancel:"
+            }
+            Some(PromptKind::Seed) => {
+                "Type a seed, Enter to generate a track from it, Escape to cancel:"
+            }
         };
+        text.0 = format!(
+            "{title}\n{}\n{}",
+            prompt.text,
+            prompt.error.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Imports the track in a share `code` as a new track of the open file, and switches to it.
+fn import_track(
+    code: &str,
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+) -> Result<(), String> {
+    let track = RaceTrack::from_share_code(code).map_err(|err| err.to_string())?;
+    info!("Imported {}", track.track_name);
+    add_track(track, history, control_points, tracks_asset);
+    Ok(())
+}
+
+/// Renames the current track to `name`.
+fn rename_track(
+    name: &str,
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("A track needs a name".to_string());
+    }
+    let Some(track) = tracks_asset.get_current_track() else {
+        return Ok(());
+    };
+    if track.track_name == name {
+        return Ok(());
+    }
+    if tracks_asset.tracks.iter().any(|other| other.track_name == name) {
+        return Err(format!("There already is a track called \"{name}\""));
+    }
+    let command = EditCommand::RenameTrack {
+        from: track.track_name.clone(),
+        to: name.to_string(),
+    };
+    history.perform(command, control_points, tracks_asset);
+    Ok(())
+}
+
+/// Gives the corner covering `distance` the name `name`, or just its number if `name` is blank.
+fn name_corner(
+    distance: f32,
+    name: &str,
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+) {
+    let name = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+    edit_track(history, control_points, tracks_asset, |track| {
+        track.rename_corner(distance, name);
+    });
+}
+
+/// Replaces the current track with one generated from the seed in `text`.
+fn generate_from_seed(
+    text: &str,
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+) -> Result<(), String> {
+    let seed = text
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("\"{}\" is not a seed, which is a whole number", text.trim()))?;
+    let generated =
+        generate_track(seed, &GeneratorSettings::default()).map_err(|err| err.to_string())?;
+    info!("Generated track from seed {seed}");
+    edit_track(history, control_points, tracks_asset, |track| {
+        *track = generated;
+    });
+    control_points.selected = None;
+    Ok(())
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn copy_to_clipboard(text: String) {
+    if let Err(err) = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        warn!("Could not copy to the clipboard: {err}");
+    }
+}
 
-        let tangent = tangent.normalize_or_zero();
+#[cfg(target_family = "wasm")]
+fn copy_to_clipboard(_text: String) {}
 
-        let normal = tangent.rotate(Vec2::from_angle(std::f32::consts::PI / -2.0)) * 20.0; // 90° rotation
-        let normal2 = normal.rotate(Vec2::from_angle(std::f32::consts::PI));
+#[cfg(not(target_family = "wasm"))]
+fn paste_from_clipboard() -> String {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .unwrap_or_else(|err| {
+            warn!("Could not paste from the clipboard: {err}");
+            String::new()
+        })
+}
+
+#[cfg(target_family = "wasm")]
+fn paste_from_clipboard() -> String {
+    String::new()
+}
+
+// -----------------------------------
+// Validation Resources and Systems
+// -----------------------------------
+
+/// The problems found with the track being edited the last time it changed.
+#[derive(Clone, Default, Resource)]
+struct TrackIssues(Vec<TrackIssue>);
+
+/// Marks the text listing the [`TrackIssues`].
+#[derive(Component)]
+struct IssuesText;
 
-        normals.push((control_points[i] + normal, control_points[i] + normal2)); 
+/// This system validates the track whenever the [control points] change, and lists the issues
+/// found below the instructions.
+///
+/// [control points]: ControlPoints
+fn validate_track(
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    mut issues: ResMut<TrackIssues>,
+    mut text: Single<&mut Text, With<IssuesText>>,
+) {
+    if !control_points.is_changed() {
+        return;
     }
 
-    normals
+    issues.0 = editing_track(&control_points, &tracks_asset).validate();
+    text.0 = issues
+        .0
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+/// Marks the text showing the statistics of the track being edited.
+#[derive(Component)]
+struct StatsText;
+
+/// This system measures the track whenever the [control points] change and shows its statistics
+/// below the instructions.
+///
+/// [control points]: ControlPoints
+fn update_stats_text(
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    mut text: Single<&mut Text, With<StatsText>>,
+) {
+    if !control_points.is_changed() {
+        return;
+    }
+
+    let track = editing_track(&control_points, &tracks_asset);
+    let Some(stats) = track.stats() else {
+        text.0 = String::new();
+        return;
+    };
+    let length = stats.length;
+    let corners = track
+        .corners()
+        .iter()
+        .map(|corner| {
+            let name = track.corner_name(corner, length);
+            format!("{name} ({} {})", corner.direction, corner.severity)
+        })
+        .collect::<Vec<_>>();
+    text.0 = format!("{stats}\n{}", corners.join(", "));
+}
+
+/// This system marks the apex of every corner of the track being edited, coloured by how
+/// severe the corner is.
+fn draw_corners(
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    mut gizmos: Gizmos,
+) {
+    let track = editing_track(&control_points, &tracks_asset);
+    let Some(table) = track.arc_length_table() else {
+        return;
+    };
+    for corner in track.corners() {
+        let color = match corner.severity {
+            CornerSeverity::Fast => Color::srgb(0.3, 1.0, 0.3),
+            CornerSeverity::Medium => Color::srgb(1.0, 1.0, 0.3),
+            CornerSeverity::Slow => Color::srgb(1.0, 0.6, 0.2),
+            CornerSeverity::Hairpin => Color::srgb(1.0, 0.2, 0.2),
+        };
+        gizmos.cross_2d(table.position_at(corner.apex), 8.0, color);
+    }
+}
+
+/// This system uses gizmos to highlight where the [`TrackIssues`] are.
+fn draw_track_issues(issues: Res<TrackIssues>, mut gizmos: Gizmos) {
+    for issue in &issues.0 {
+        gizmos.circle_2d(issue.location, 16.0, Color::srgb(1.0, 0.2, 0.2));
+        gizmos.circle_2d(issue.location, 20.0, Color::srgb(1.0, 0.2, 0.2));
+    }
 }
 
 // -----------------------------------
@@ -258,11 +841,24 @@ struct MouseEditMove {
     start: Option<Vec2>,
 }
 
+/// The control point being dragged with the left mouse button.
 #[derive(Clone, Default, Resource)]
-struct MouseMoveMove {
-    start: Option<Vec2>,
+struct PointDrag {
+    /// The index of the point being dragged, and where it was before the drag started.
+    dragging: Option<(usize, Vec2)>,
+    /// From the cursor to the point, so the point does not jump to the cursor when it is grabbed.
+    offset: Vec2,
 }
 
+/// The control point under the cursor, which a click would pick.
+#[derive(Clone, Default, Resource)]
+struct HoveredPoint(Option<usize>);
+
+/// Where a click would insert a control point into the track, if the cursor is on the road and not
+/// over a control point.
+#[derive(Clone, Default, Resource)]
+struct InsertPreview(Option<PointInsertion>);
+
 /// The current mouse position, if known.
 #[derive(Clone, Default, Resource)]
 struct MousePosition(Option<Vec2>);
@@ -277,93 +873,689 @@ fn handle_mouse_move(
     }
 }
 
-/// This system handles updating the [`MouseEditMove`] resource, orchestrating the logical part
-/// of the click-and-drag motion which actually creates new control points.
+/// This system finds the [`HoveredPoint`]. The point being dragged stays hovered even when the
+/// cursor gets ahead of it.
+fn update_hovered_point(
+    mouse_position: Res<MousePosition>,
+    control_points: Res<ControlPoints>,
+    prop_editing: Res<PropEditing>,
+    drag: Res<PointDrag>,
+    mut hovered: ResMut<HoveredPoint>,
+    view: Res<EditorView>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) {
+    let (camera, camera_transform) = *camera;
+    let hovered_now = if prop_editing.active || view.panning {
+        None
+    } else if let Some((index, _)) = drag.dragging {
+        Some(index)
+    } else {
+        mouse_position
+            .0
+            .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok())
+            .and_then(|position| control_points.point_near(position))
+    };
+    if hovered.0 != hovered_now {
+        hovered.0 = hovered_now;
+    }
+}
+
+/// This system finds the [`InsertPreview`].
+fn update_insert_preview(
+    mouse_position: Res<MousePosition>,
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    prop_editing: Res<PropEditing>,
+    hovered: Res<HoveredPoint>,
+    view: Res<EditorView>,
+    mut preview: ResMut<InsertPreview>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) {
+    let (camera, camera_transform) = *camera;
+    let cursor = mouse_position
+        .0
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok());
+    let preview_now = match cursor {
+        Some(cursor) if !prop_editing.active && !view.panning && hovered.0.is_none() => {
+            editing_track(&control_points, &tracks_asset).insertion_near(cursor)
+        }
+        _ => None,
+    };
+    if preview.0 != preview_now {
+        preview.0 = preview_now;
+    }
+}
+
+/// This system handles the left mouse button. Pressing it on a control point selects the point
+/// and drags it around until the button is released, anywhere else it starts the click-and-drag
+/// motion of [`MouseEditMove`] which creates a new control point. The new point is inserted into
+/// the track where it was clicked if that is on the road, and added to the end otherwise.
 fn handle_mouse_press(
     mut button_events: EventReader<MouseButtonInput>,
     mouse_position: Res<MousePosition>,
+    hovered: Res<HoveredPoint>,
     mut edit_move: ResMut<MouseEditMove>,
-    mut move_move: ResMut<MouseMoveMove>,
+    mut drag: ResMut<PointDrag>,
     mut control_points: ResMut<ControlPoints>,
+    mut tracks_asset: ResMut<TracksAsset>,
+    mut history: ResMut<EditHistory>,
+    prop_editing: Res<PropEditing>,
+    view: Res<EditorView>,
+    grid: Res<EditorGrid>,
     camera: Single<(&Camera, &GlobalTransform)>,
 ) {
+    if prop_editing.active || view.panning {
+        button_events.clear();
+        // A click that started before panning began does not add a point when it ends.
+        edit_move.start = None;
+        return;
+    }
     let Some(mouse_pos) = mouse_position.0 else {
         return;
     };
+    let (camera, camera_transform) = *camera;
+    let cursor = camera.viewport_to_world_2d(camera_transform, mouse_pos).ok();
 
     // Handle click and drag behavior
     for button_event in button_events.read() {
-        match button_event.button {
-            MouseButton::Left => {
-                match button_event.state {
-                    ButtonState::Pressed => {
-                        if edit_move.start.is_some() {
-                            // If the edit move already has a start, press event should do nothing.
-                            continue;
-                        }
-                        // This press represents the start of the edit move.
-                        edit_move.start = Some(mouse_pos);
+        if button_event.button != MouseButton::Left {
+            continue;
+        }
+        match button_event.state {
+            ButtonState::Pressed => {
+                if edit_move.start.is_some() || drag.dragging.is_some() {
+                    // If a move has already started, press event should do nothing.
+                    continue;
+                }
+                let grabbed = hovered
+                    .0
+                    .and_then(|index| Some((index, *control_points.points.get(index)?, cursor?)));
+                if let Some((index, point, cursor)) = grabbed {
+                    control_points.selected = Some(index);
+                    drag.dragging = Some((index, point));
+                    drag.offset = point - cursor;
+                } else {
+                    // This press represents the start of the edit move.
+                    edit_move.start = Some(mouse_pos);
+                }
+            }
+
+            ButtonState::Released => {
+                // The point has followed the cursor all along, so the drag only needs to be
+                // remembered for undo.
+                if let Some((index, from)) = drag.dragging.take() {
+                    if let Some(&to) = control_points.points.get(index).filter(|&&to| to != from) {
+                        history.record(EditCommand::MovePoint { index, from, to }, &tracks_asset);
                     }
+                    continue;
+                }
 
-                    ButtonState::Released => {
-                        // Release is only meaningful if we started an edit move.
-                        let Some(start) = edit_move.start else {
-                            continue;
-                        };
+                // Release is only meaningful if we started an edit move.
+                let Some(start) = edit_move.start else {
+                    continue;
+                };
 
-                        let (camera, camera_transform) = *camera;
+                // Convert the starting point into world coords:
+                let Ok(point) = camera.viewport_to_world_2d(camera_transform, start) else {
+                    continue;
+                };
+                // The start of the click-and-drag motion represents the point to add.
+                let track = editing_track(&control_points, &tracks_asset);
+                let command = add_point_command(&track, &grid, point);
+                history.perform(command, &mut control_points, &mut tracks_asset);
 
-                        // Convert the starting point and end point (current mouse pos) into world coords:
-                        let Ok(point) = camera.viewport_to_world_2d(camera_transform, start) else {
-                            continue;
-                        };
-                        // The start of the click-and-drag motion represents the point to add,
-                        // while the difference with the current position represents the tangent.
-                        control_points.points.push(point);
+                // Reset the edit move since we've consumed it.
+                edit_move.start = None;
+            }
+        }
+    }
 
-                        // Reset the edit move since we've consumed it.
-                        edit_move.start = None;
-                    }
-                }
+    // The dragged point follows the cursor, so the curve and the road are rebuilt as it moves.
+    if let Some(cursor) = cursor {
+        follow_cursor(&mut control_points, &drag, &grid, cursor);
+    }
+}
+
+/// The command adding a point clicked at `point` to `track`. It is inserted into the track where
+/// the click is on the road, and added to the end otherwise. In mirror mode its mirror image is
+/// added too: on the road where the image falls on it, or else both go in the middle of the
+/// points, so a track built from both ends towards the middle is symmetric all the way round.
+fn add_point_command(track: &RaceTrack, grid: &EditorGrid, point: Vec2) -> EditCommand {
+    let width = track.widths.last().copied().unwrap_or(DEFAULT_TRACK_WIDTH);
+    let insert = |insertion: PointInsertion| EditCommand::InsertPoint {
+        index: insertion.index,
+        point: insertion.point,
+        width: insertion.width,
+    };
+    let Some(insertion) = track.insertion_near(point) else {
+        let placed = grid.place(point, track.points.iter().copied());
+        let count = track.points.len();
+        return match grid.mirror(placed).filter(|&mirrored| mirrored != placed) {
+            Some(mirrored) => {
+                let index = count / 2;
+                EditCommand::Group(vec![
+                    EditCommand::InsertPoint { index, point: placed, width },
+                    EditCommand::InsertPoint {
+                        index: count + 1 - index,
+                        point: mirrored,
+                        width,
+                    },
+                ])
+            }
+            None => EditCommand::InsertPoint {
+                index: count,
+                point: placed,
+                width,
             },
-            MouseButton::Right => {
-                if control_points.selected.is_none() {
-                    continue;
-                }
-                match button_event.state {
-                    ButtonState::Pressed => {
-                        if move_move.start.is_some() {
-                            // If the edit move already has a start, press event should do nothing.
-                            continue;
-                        }
-                        // This press represents the start of the edit move.
-                        move_move.start = Some(mouse_pos);
-                    }
+        };
+    };
 
-                    ButtonState::Released => {
-                        // Release is only meaningful if we started an edit move.
-                        let Some(start) = move_move.start else {
-                            continue;
-                        };
-
-                        let (camera, camera_transform) = *camera;
-
-                        // Convert the starting point and end point (current mouse pos) into world coords:
-                        let Ok(point) = camera.viewport_to_world_2d(camera_transform, start) else {
-                            continue;
-                        };
-                        // The start of the click-and-drag motion represents the point to add,
-                        // while the difference with the current position represents the tangent.
-                        let selected = control_points.selected.unwrap();
-                        let to_mutate = control_points.points.get_mut(selected).unwrap();
-                        *to_mutate = point;
-
-                        // Reset the edit move since we've consumed it.
-                        move_move.start = None;
-                    }
-                }
+    let insertion = PointInsertion {
+        point: grid.place(insertion.point, []),
+        ..insertion
+    };
+    let mirrored = grid
+        .mirror(insertion.point)
+        .filter(|&mirrored| mirrored.distance(insertion.point) > POINT_PICK_RADIUS)
+        .and_then(|mirrored| {
+            let mut track = track.clone();
+            track.insert_point(insertion.index, insertion.point, insertion.width);
+            track.insertion_near(mirrored)
+        });
+    match mirrored {
+        Some(mirrored) => EditCommand::Group(vec![insert(insertion), insert(mirrored)]),
+        None => insert(insertion),
+    }
+}
+
+/// Moves the point being dragged to the `cursor`, snapped to the grid or lined up with the other
+/// points. Only the dragged point moves, in mirror mode too.
+fn follow_cursor(
+    control_points: &mut ControlPoints,
+    drag: &PointDrag,
+    grid: &EditorGrid,
+    cursor: Vec2,
+) {
+    let Some((index, _)) = drag.dragging else {
+        return;
+    };
+    let others = control_points
+        .points
+        .iter()
+        .enumerate()
+        .filter(|&(other, _)| other != index)
+        .map(|(_, &point)| point);
+    let point = grid.place(cursor + drag.offset, others);
+    if control_points.points.get(index).is_some_and(|&current| current != point) {
+        control_points.points[index] = point;
+    }
+}
+
+// -----------------------------------
+// Camera-related Resources and Systems
+// -----------------------------------
+
+/// How much one notch of the scroll wheel zooms.
+const ZOOM_STEP: f32 = 1.1;
+
+/// How many pixels of smooth scrolling count as one notch of the scroll wheel.
+const PIXELS_PER_SCROLL_LINE: f32 = 100.0;
+
+/// How far the view can zoom in and out, as world units per pixel.
+const MIN_ZOOM_SCALE: f32 = 0.1;
+const MAX_ZOOM_SCALE: f32 = 20.0;
+
+/// How much bigger than the track the view is when the track is framed.
+const FRAME_MARGIN: f32 = 1.1;
+
+/// Whether the view is being panned, in which case the mouse does not edit the track.
+#[derive(Clone, Default, Resource)]
+struct EditorView {
+    panning: bool,
+}
+
+/// This system pans the view while the middle mouse button is held, or space and the left mouse
+/// button, zooms it around the cursor with the scroll wheel and fits the whole track in it with
+/// Home.
+fn control_camera(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    mouse_position: Res<MousePosition>,
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    prompt: Res<TextPrompt>,
+    mut view: ResMut<EditorView>,
+    camera: Single<(&Camera, &mut Transform, &mut Projection)>,
+) {
+    let (camera, mut transform, mut projection) = camera.into_inner();
+    let Projection::Orthographic(projection) = projection.as_mut() else {
+        return;
+    };
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+
+    let space = !prompt.is_open() && keyboard.pressed(KeyCode::Space);
+    let middle = mouse_buttons.pressed(MouseButton::Middle);
+    if view.panning != (space || middle) {
+        view.panning = space || middle;
+    }
+
+    let mut translation = transform.translation.truncate();
+    let mut scale = projection.scale;
+    // The y axis of the viewport points down, the one of the world up.
+    let flip_y = vec2(1.0, -1.0);
+
+    if middle || (space && mouse_buttons.pressed(MouseButton::Left)) {
+        translation -= mouse_motion.delta * flip_y * scale;
+    }
+
+    let notches = match mouse_scroll.unit {
+        MouseScrollUnit::Line => mouse_scroll.delta.y,
+        MouseScrollUnit::Pixel => mouse_scroll.delta.y / PIXELS_PER_SCROLL_LINE,
+    };
+    if notches != 0.0 {
+        let zoomed = (scale * ZOOM_STEP.powf(-notches)).clamp(MIN_ZOOM_SCALE, MAX_ZOOM_SCALE);
+        // Keep the point under the cursor where it is.
+        if let Some(cursor) = mouse_position.0 {
+            translation += (cursor - viewport_size / 2.0) * flip_y * (scale - zoomed);
+        }
+        scale = zoomed;
+    }
+
+    if keyboard.just_pressed(KeyCode::Home) && !prompt.is_open() {
+        let track = editing_track(&control_points, &tracks_asset);
+        if let Some((center, framed)) = frame_track(&track, viewport_size) {
+            translation = center;
+            scale = framed;
+        }
+    }
+
+    if translation != transform.translation.truncate() {
+        transform.translation = translation.extend(transform.translation.z);
+    }
+    if scale != projection.scale {
+        projection.scale = scale;
+    }
+}
+
+/// The center and zoom of a view `viewport_size` pixels big that shows the whole of `track`, or
+/// `None` if the track has no control points.
+fn frame_track(track: &RaceTrack, viewport_size: Vec2) -> Option<(Vec2, f32)> {
+    let points = track
+        .arc_length_table()
+        .map(|table| table.uniform_positions(TRACK_SAMPLE_SPACING))
+        .unwrap_or_else(|| track.points.clone());
+    let first = *points.first()?;
+    let half_width = track.point_widths().into_iter().fold(0.0, f32::max) / 2.0;
+    let bounds = points
+        .iter()
+        .fold(Rect::from_center_size(first, Vec2::ZERO), |bounds, &point| {
+            bounds.union_point(point)
+        })
+        .inflate(half_width);
+    let scale = (bounds.size() * FRAME_MARGIN / viewport_size)
+        .max_element()
+        .clamp(MIN_ZOOM_SCALE, MAX_ZOOM_SCALE);
+    Some((bounds.center(), scale))
+}
+
+/// Puts the camera back where the rest of the game expects it when leaving the editor.
+fn reset_camera(camera: Single<(&mut Transform, &mut Projection), With<Camera>>) {
+    let (mut transform, mut projection) = camera.into_inner();
+    transform.translation.x = 0.0;
+    transform.translation.y = 0.0;
+    if let Projection::Orthographic(projection) = projection.as_mut() {
+        projection.scale = 1.0;
+    }
+}
+
+// -----------------------------------
+// Grid-related Resources and Systems
+// -----------------------------------
+
+/// The grid spacings to choose from, in world units.
+const GRID_SPACINGS: [f32; 6] = [10.0, 20.0, 25.0, 50.0, 100.0, 200.0];
+
+/// The index in [`GRID_SPACINGS`] of the spacing the editor starts with.
+const DEFAULT_GRID_SPACING: usize = 3;
+
+/// The grid is not drawn when it would take more lines than this to cover the view.
+const MAX_GRID_LINES: u32 = 400;
+
+/// How close to lining up with another control point a point has to be to line up with it, when
+/// it does not snap to the grid.
+const ALIGNMENT_DISTANCE: f32 = 6.0;
+
+/// The line new points are mirrored about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MirrorAxis {
+    Vertical,
+    Horizontal,
+}
+
+/// The grid control points snap to, and how new points are mirrored.
+#[derive(Clone, Resource)]
+struct EditorGrid {
+    /// Whether the grid is shown and points snap to it.
+    snap: bool,
+    /// The index of the spacing in [`GRID_SPACINGS`].
+    spacing: usize,
+    /// The axis points are mirrored about, or `None` when they are not mirrored.
+    mirror: Option<MirrorAxis>,
+    /// A point on the mirror axis.
+    mirror_origin: Vec2,
+}
+
+impl Default for EditorGrid {
+    fn default() -> Self {
+        Self {
+            snap: false,
+            spacing: DEFAULT_GRID_SPACING,
+            mirror: None,
+            mirror_origin: Vec2::ZERO,
+        }
+    }
+}
+
+impl EditorGrid {
+    fn spacing(&self) -> f32 {
+        GRID_SPACINGS[self.spacing]
+    }
+
+    /// Where a point put at `position` ends up: on the nearest grid crossing when snapping,
+    /// otherwise lined up with any of `others` it is nearly level with.
+    fn place(&self, position: Vec2, others: impl IntoIterator<Item = Vec2>) -> Vec2 {
+        if self.snap {
+            return (position / self.spacing()).round() * self.spacing();
+        }
+        let mut placed = position;
+        let mut closest = Vec2::splat(ALIGNMENT_DISTANCE);
+        for other in others {
+            let offset = (other - position).abs();
+            if offset.x <= closest.x {
+                closest.x = offset.x;
+                placed.x = other.x;
+            }
+            if offset.y <= closest.y {
+                closest.y = offset.y;
+                placed.y = other.y;
+            }
+        }
+        placed
+    }
+
+    /// The mirror image of `point`, or `None` when points are not mirrored.
+    fn mirror(&self, point: Vec2) -> Option<Vec2> {
+        match self.mirror? {
+            MirrorAxis::Vertical => Some(vec2(2.0 * self.mirror_origin.x - point.x, point.y)),
+            MirrorAxis::Horizontal => Some(vec2(point.x, 2.0 * self.mirror_origin.y - point.y)),
+        }
+    }
+}
+
+/// This system handles the keys for the grid and mirroring.
+fn handle_grid_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_position: Res<MousePosition>,
+    prompt: Res<TextPrompt>,
+    mut grid: ResMut<EditorGrid>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) {
+    if prompt.is_open() {
+        return;
+    }
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keyboard.just_pressed(KeyCode::KeyQ) {
+        grid.snap = !grid.snap;
+    }
+    if keyboard.just_pressed(KeyCode::BracketLeft) {
+        grid.spacing = grid.spacing.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::BracketRight) {
+        grid.spacing = (grid.spacing + 1).min(GRID_SPACINGS.len() - 1);
+    }
+    if keyboard.just_pressed(KeyCode::KeyM) && shift {
+        let (camera, camera_transform) = *camera;
+        let cursor = mouse_position
+            .0
+            .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok());
+        if let Some(cursor) = cursor {
+            grid.mirror_origin = grid.place(cursor, []);
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyM) {
+        grid.mirror = match grid.mirror {
+            None => Some(MirrorAxis::Vertical),
+            Some(MirrorAxis::Vertical) => Some(MirrorAxis::Horizontal),
+            Some(MirrorAxis::Horizontal) => None,
+        };
+    }
+}
+
+/// This system uses gizmos to draw the grid over the part of the world in view, and the mirror
+/// axis.
+fn draw_grid(grid: Res<EditorGrid>, mut gizmos: Gizmos, camera: Single<(&Camera, &GlobalTransform)>) {
+    let (camera, camera_transform) = *camera;
+    let Some(viewport) = camera.logical_viewport_rect() else {
+        return;
+    };
+    let corners = [viewport.min, viewport.max]
+        .map(|corner| camera.viewport_to_world_2d(camera_transform, corner).ok());
+    let [Some(min), Some(max)] = corners else {
+        return;
+    };
+    let view = Rect::from_corners(min, max);
+
+    if grid.snap {
+        let spacing = grid.spacing();
+        // An even number of cells centred on a grid line puts every line on the grid.
+        let cells = ((view.size() / spacing / 2.0).ceil().as_uvec2() + UVec2::ONE) * 2;
+        if cells.max_element() <= MAX_GRID_LINES {
+            let center = (view.center() / spacing).round() * spacing;
+            gizmos.grid_2d(
+                Isometry2d::from_translation(center),
+                cells,
+                Vec2::splat(spacing),
+                Color::srgba(1.0, 1.0, 1.0, 0.1),
+            );
+        }
+    }
+
+    let origin = grid.mirror_origin;
+    let axis = match grid.mirror {
+        None => return,
+        Some(MirrorAxis::Vertical) => (vec2(origin.x, view.min.y), vec2(origin.x, view.max.y)),
+        Some(MirrorAxis::Horizontal) => (vec2(view.min.x, origin.y), vec2(view.max.x, origin.y)),
+    };
+    gizmos.line_2d(axis.0, axis.1, Color::srgb(1.0, 0.5, 0.0));
+}
+
+/// This system draws a guide from the point being dragged or added to every other control point it
+/// lines up with.
+fn draw_alignment_guides(
+    control_points: Res<ControlPoints>,
+    drag: Res<PointDrag>,
+    edit_move: Res<MouseEditMove>,
+    grid: Res<EditorGrid>,
+    mut gizmos: Gizmos,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) {
+    let (camera, camera_transform) = *camera;
+    let points = &control_points.points;
+    let (position, skipped) = if let Some((index, _)) = drag.dragging {
+        let Some(&position) = points.get(index) else {
+            return;
+        };
+        (position, Some(index))
+    } else if let Some(start) = edit_move.start {
+        let Ok(start) = camera.viewport_to_world_2d(camera_transform, start) else {
+            return;
+        };
+        (grid.place(start, points.iter().copied()), None)
+    } else {
+        return;
+    };
+
+    for (index, &other) in points.iter().enumerate() {
+        if skipped == Some(index) {
+            continue;
+        }
+        if other.x == position.x || other.y == position.y {
+            gizmos.line_2d(position, other, Color::srgba(0.4, 0.8, 1.0, 0.6));
+        }
+    }
+}
+
+// -----------------------------------
+// Prop-related Resources and Systems
+// -----------------------------------
+
+/// Whether clicks place props instead of control points, and which prop they place.
+#[derive(Clone, Resource)]
+struct PropEditing {
+    active: bool,
+    kind: PropKind,
+    /// The prop being dragged with the right mouse button.
+    moving: Option<usize>,
+}
+
+impl Default for PropEditing {
+    fn default() -> Self {
+        Self {
+            active: false,
+            kind: PropKind::TyreStack,
+            moving: None,
+        }
+    }
+}
+
+/// This system places, moves and deletes props while in prop mode.
+fn handle_prop_input(
+    mut button_events: EventReader<MouseButtonInput>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_position: Res<MousePosition>,
+    mut prop_editing: ResMut<PropEditing>,
+    control_points: Res<ControlPoints>,
+    mut tracks_asset: ResMut<TracksAsset>,
+    mut history: ResMut<EditHistory>,
+    view: Res<EditorView>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    prompt: Res<TextPrompt>,
+) {
+    if !prop_editing.active || prompt.is_open() || view.panning {
+        button_events.clear();
+        return;
+    }
+    let digits = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4];
+    for (key, kind) in digits.into_iter().zip(PropKind::ALL) {
+        if keyboard.just_pressed(key) {
+            prop_editing.kind = kind;
+        }
+    }
+
+    let (camera, camera_transform) = *camera;
+    let Some(mouse_pos) = mouse_position
+        .0
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok())
+    else {
+        return;
+    };
+    // Props are edited on the track directly, and remembered as one change to the whole track.
+    let before = editing_track(&control_points, &tracks_asset);
+    let Some(table) = before.arc_length_table() else {
+        return;
+    };
+    let Some(track) = tracks_asset.get_current_track_mut() else {
+        return;
+    };
+
+    let deleted = keyboard
+        .just_pressed(KeyCode::KeyX)
+        .then(|| track.prop_near(&table, mouse_pos))
+        .flatten();
+    if let Some(index) = deleted {
+        track.props.remove(index);
+        prop_editing.moving = None;
+    }
+
+    for button_event in button_events.read() {
+        match (button_event.button, button_event.state) {
+            (MouseButton::Left, ButtonState::Released) => {
+                let position = table.closest_point(mouse_pos);
+                track.props.push(TrackProp::at(prop_editing.kind, position));
+            }
+            (MouseButton::Right, ButtonState::Pressed) => {
+                prop_editing.moving = track.prop_near(&table, mouse_pos);
             }
-                _ => continue,
+            (MouseButton::Right, ButtonState::Released) => {
+                let Some(prop) = prop_editing
+                    .moving
+                    .take()
+                    .and_then(|index| track.props.get_mut(index))
+                else {
+                    continue;
+                };
+                let position = table.closest_point(mouse_pos);
+                prop.distance = position.distance;
+                prop.lateral_offset = position.lateral_offset;
+            }
+            _ => {}
+        }
+    }
+
+    let after = editing_track(&control_points, &tracks_asset);
+    if after != before {
+        let command = EditCommand::EditTrack {
+            before: Box::new(before),
+            after: Box::new(after),
+        };
+        history.record(command, &tracks_asset);
+    }
+}
+
+/// This system draws the racing line through the track being edited, if it is switched on.
+fn draw_racing_line(
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    show_racing_line: Res<ShowRacingLine>,
+    mut racing_lines: ResMut<RacingLineCache>,
+    mut gizmos: Gizmos,
+) {
+    if !show_racing_line.0 {
+        return;
+    }
+    let track = editing_track(&control_points, &tracks_asset);
+    if let Some(line) = racing_lines.get(&track) {
+        line.draw(&mut gizmos, Color::srgb(0.2, 1.0, 0.3));
+    }
+}
+
+/// This system uses gizmos to draw the props of the track being edited, highlighting the one
+/// being moved.
+fn draw_props(
+    control_points: Res<ControlPoints>,
+    tracks_asset: Res<TracksAsset>,
+    prop_editing: Res<PropEditing>,
+    mut gizmos: Gizmos,
+) {
+    let track = editing_track(&control_points, &tracks_asset);
+    let Some(table) = track.arc_length_table() else {
+        return;
+    };
+    for (index, prop) in track.props.iter().enumerate() {
+        let transform = prop.transform(&table);
+        let position = transform.translation.truncate();
+        let size = prop.kind.size();
+        if prop.kind.is_round() {
+            gizmos.circle_2d(position, size.x / 2.0, prop.kind.color());
+        } else {
+            let rotation = Rot2::radians(transform.rotation.to_euler(EulerRot::ZYX).0);
+            gizmos.rect_2d(Isometry2d::new(position, rotation), size, prop.kind.color());
+        }
+        if prop_editing.moving == Some(index) {
+            gizmos.circle_2d(position, PROP_PICK_RADIUS, Color::srgb(1.0, 1.0, 0.0));
         }
     }
 }
@@ -372,6 +1564,8 @@ fn handle_mouse_press(
 fn draw_edit_move(
     edit_move: Res<MouseEditMove>,
     mouse_position: Res<MousePosition>,
+    control_points: Res<ControlPoints>,
+    grid: Res<EditorGrid>,
     mut gizmos: Gizmos,
     camera: Single<(&Camera, &GlobalTransform)>,
 ) {
@@ -392,89 +1586,311 @@ fn draw_edit_move(
     let Ok(end) = camera.viewport_to_world_2d(camera_transform, mouse_pos) else {
         return;
     };
+    let start = grid.place(start, control_points.points.iter().copied());
 
     gizmos.circle_2d(start, 10.0, Color::srgb(0.0, 1.0, 0.7));
     gizmos.circle_2d(start, 7.0, Color::srgb(0.0, 1.0, 0.7));
     gizmos.arrow_2d(start, end, Color::srgb(1.0, 0.0, 0.7));
 }
 
+/// This system draws where a click would insert a control point, joined to the control points it
+/// would go between.
+fn draw_insert_preview(
+    preview: Res<InsertPreview>,
+    edit_move: Res<MouseEditMove>,
+    control_points: Res<ControlPoints>,
+    mut gizmos: Gizmos,
+) {
+    let Some(insertion) = preview.0 else {
+        return;
+    };
+    if edit_move.start.is_some() {
+        return;
+    }
+    let color = Color::srgb(0.0, 1.0, 0.7);
+    let points = &control_points.points;
+    let neighbours = [
+        insertion.index.checked_sub(1),
+        Some(insertion.index % points.len().max(1)),
+    ];
+    for neighbour in neighbours.into_iter().flatten() {
+        if let Some(&neighbour) = points.get(neighbour) {
+            gizmos.line_2d(insertion.point, neighbour, color.with_alpha(0.4));
+        }
+    }
+    gizmos.circle_2d(insertion.point, CONTROL_POINT_RADIUS, color);
+}
+
 /// This system handles all keyboard commands.
 fn handle_keypress(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut control_points: ResMut<ControlPoints>,
-    mut tracks_asset: ResMut<TracksAsset>
+    mut tracks_asset: ResMut<TracksAsset>,
+    mut history: ResMut<EditHistory>,
+    mut prop_editing: ResMut<PropEditing>,
+    mut files: ResMut<EditorFiles>,
+    mut show_racing_line: ResMut<ShowRacingLine>,
+    prompt: Res<TextPrompt>,
 ) {
-    // R => remove last control point
-    if keyboard.just_pressed(KeyCode::KeyR) {
-        if control_points.selected.is_some() {
-            let selected = control_points.selected.unwrap();
-            control_points.points.remove(selected);
-            control_points.selected = None;
+    // Keys go into the text prompt while it is open.
+    if prompt.is_open() {
+        return;
+    }
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if ctrl && keyboard.just_pressed(KeyCode::KeyZ) {
+        let done = if shift {
+            history.redo(&mut control_points, &mut tracks_asset)
         } else {
-            control_points.points.pop();
+            history.undo(&mut control_points, &mut tracks_asset)
+        };
+        if !done {
+            info!("Nothing to {}", if shift { "redo" } else { "undo" });
         }
-
+    }
+    // R => remove the selected control point, or the last one if none is selected
+    if keyboard.just_pressed(KeyCode::KeyR) && !ctrl {
+        let index = control_points
+            .selected
+            .or_else(|| control_points.points.len().checked_sub(1));
+        let removed = index.and_then(|index| {
+            let point = *control_points.points.get(index)?;
+            let width = *control_points.widths.get(index)?;
+            Some(EditCommand::DeletePoint { index, point, width })
+        });
+        if let Some(command) = removed {
+            history.perform(command, &mut control_points, &mut tracks_asset);
+        }
+    }
+    if keyboard.any_just_pressed([KeyCode::Equal, KeyCode::NumpadAdd]) {
+        change_selected_width(&mut history, &mut control_points, &mut tracks_asset, WIDTH_STEP);
+    }
+    if keyboard.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        change_selected_width(&mut history, &mut control_points, &mut tracks_asset, -WIDTH_STEP);
+    }
+    if keyboard.just_pressed(KeyCode::KeyF) {
+        edit_at_selected(&mut history, &mut control_points, &mut tracks_asset, RaceTrack::set_start_line);
+    }
+    if keyboard.just_pressed(KeyCode::KeyC) && !ctrl {
+        edit_at_selected(&mut history, &mut control_points, &mut tracks_asset, RaceTrack::toggle_checkpoint);
+    }
+    if keyboard.just_pressed(KeyCode::KeyE) {
+        edit_at_selected(&mut history, &mut control_points, &mut tracks_asset, RaceTrack::set_finish_line);
+    }
+    if keyboard.just_pressed(KeyCode::KeyB) {
+        // Bridges are measured along the track as it is being edited, not as it was last saved.
+        edit_at_selected(&mut history, &mut control_points, &mut tracks_asset, RaceTrack::toggle_bridge);
+    }
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        prop_editing.active = !prop_editing.active;
+        prop_editing.moving = None;
+    }
+    if keyboard.just_pressed(KeyCode::KeyI) {
+        show_racing_line.0 = !show_racing_line.0;
+    }
+    if keyboard.just_pressed(KeyCode::KeyO) {
+        edit_track(&mut history, &mut control_points, &mut tracks_asset, RaceTrack::toggle_closed);
     }
     if keyboard.just_pressed(KeyCode::KeyS) {
-        save_to_file(&control_points, &mut tracks_asset, "assets/race.tracks");
+        let target = files.target.clone();
+        save_to_file(&control_points, &mut tracks_asset, &mut files, &target);
+    }
+    if keyboard.just_pressed(KeyCode::KeyT) {
+        files.next_target();
+    }
+    if keyboard.just_pressed(KeyCode::KeyL) {
+        match library::load_file(&files.open) {
+            Ok(loaded) => {
+                open_tracks(loaded, &mut tracks_asset, &mut control_points);
+                history.clear();
+            }
+            Err(err) => warn!("{err}"),
+        }
+    }
+    if keyboard.any_just_pressed([KeyCode::PageUp, KeyCode::PageDown]) {
+        let step = if keyboard.just_pressed(KeyCode::PageUp) { -1 } else { 1 };
+        if let Some(loaded) = files.open_next(step) {
+            open_tracks(loaded, &mut tracks_asset, &mut control_points);
+            history.clear();
+        }
     }
 
     if keyboard.just_pressed(KeyCode::KeyN) {
-        save_to_file(&control_points, &mut tracks_asset, "assets/race.tracks");
-        tracks_asset.new_track();
-        control_points.points = tracks_asset.get_current_track().unwrap().points.clone();
+        let open = files.open.clone();
+        save_to_file(&control_points, &mut tracks_asset, &mut files, &open);
+        let track = RaceTrack {
+            track_name: tracks_asset.new_track_name(),
+            ..default()
+        };
+        add_track(track, &mut history, &mut control_points, &mut tracks_asset);
     }
     
-    if keyboard.just_pressed(KeyCode::ArrowUp) {
-       let race_track = tracks_asset.get_next_track().unwrap();
-        control_points.points = race_track.points.clone();
+    if keyboard.any_just_pressed([KeyCode::ArrowUp, KeyCode::ArrowDown]) && !tracks_asset.tracks.is_empty() {
+        let count = tracks_asset.tracks.len();
+        let from = tracks_asset.current_track_index.unwrap_or_default();
+        let to = if keyboard.just_pressed(KeyCode::ArrowUp) {
+            (from + 1) % count
+        } else {
+            (from + count - 1) % count
+        };
+        history.perform(EditCommand::SwitchTrack { from, to }, &mut control_points, &mut tracks_asset);
     }
-    if keyboard.just_pressed(KeyCode::ArrowDown) {
-        let race_track = tracks_asset.get_prev_track().unwrap();
-        control_points.points = race_track.points.clone();
+    // There is nothing to select on a track without control points.
+    let last = control_points.points.len().checked_sub(1);
+    if let (true, Some(last)) = (keyboard.just_pressed(KeyCode::ArrowLeft), last) {
+        control_points.selected = Some(match control_points.selected {
+            None => 0,
+            Some(0) => last,
+            Some(current) => (current - 1).min(last),
+        });
     }
-    if keyboard.just_pressed(KeyCode::ArrowLeft) {
-        if control_points.selected.is_none() {
-            control_points.selected = Some(0);
-        } else {
-            let mut current = control_points.selected.unwrap();
-            if current == 0 {
-                current = control_points.points.len() - 1;
-            } else {
-                current -= 1;
-            }
-            control_points.selected = Some(current);
-        }
+    if let (true, Some(last)) = (keyboard.just_pressed(KeyCode::ArrowRight), last) {
+        control_points.selected = Some(match control_points.selected {
+            Some(current) if current < last => current + 1,
+            _ => 0,
+        });
     }
-    if keyboard.just_pressed(KeyCode::ArrowRight) {
-        if control_points.selected.is_none() {
-            control_points.selected = Some(0);
-        } else {
-            let mut current = control_points.selected.unwrap();
-            if current >= control_points.points.len() - 1 {
-                current = 0;
-            } else {
-                current += 1;
-            }
-            control_points.selected = Some(current);
-        }
+}
+
+/// Widens (or narrows, for a negative `step`) the road at the selected control point.
+fn change_selected_width(
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+    step: f32,
+) {
+    let Some(index) = control_points.selected else {
+        return;
+    };
+    let Some(&from) = control_points.widths.get(index) else {
+        return;
+    };
+    let to = (from + step).max(MIN_TRACK_WIDTH);
+    if to != from {
+        history.perform(EditCommand::SetWidth { index, from, to }, control_points, tracks_asset);
     }
 }
 
-fn save_to_file(data: &ControlPoints, tracks_asset: &mut TracksAsset, path: &str) {
-    tracks_asset.update_current_track(data.points.clone());
-    let json = serde_json::to_string_pretty(&tracks_asset).unwrap();
-    fs::write(path, json).unwrap();
+/// Applies `edit` to the current track, at the curve parameter of the selected control point.
+fn edit_at_selected(
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+    edit: fn(&mut RaceTrack, f32),
+) {
+    let Some(selected) = control_points.selected else {
+        return;
+    };
+    edit_track(history, control_points, tracks_asset, |track| {
+        edit(track, selected as f32);
+    });
 }
 
-fn load_from_file(path: &str) -> TracksAsset {
-    match fs::read_to_string(path) {
-        Ok(contents) => {
-            serde_json::from_str(&contents).unwrap()
-        }
-        Err(err) => {
-            println!("Error reading file: {}", err);
-            TracksAsset::default()
+/// Applies `edit` to the track being edited, as a single change that can be undone.
+fn edit_track(
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+    edit: impl FnOnce(&mut RaceTrack),
+) {
+    if tracks_asset.get_current_track().is_none() {
+        return;
+    }
+    let before = editing_track(control_points, tracks_asset);
+    let mut after = before.clone();
+    edit(&mut after);
+    if after != before {
+        let command = EditCommand::EditTrack {
+            before: Box::new(before),
+            after: Box::new(after),
+        };
+        history.perform(command, control_points, tracks_asset);
+    }
+}
+
+/// Adds `track` to the open file and switches to it, as a change that can be undone.
+fn add_track(
+    track: RaceTrack,
+    history: &mut EditHistory,
+    control_points: &mut ControlPoints,
+    tracks_asset: &mut TracksAsset,
+) {
+    let command = EditCommand::AddTrack {
+        index: tracks_asset.tracks.len(),
+        track: Box::new(track),
+        from: tracks_asset.current_track_index.unwrap_or_default(),
+    };
+    history.perform(command, control_points, tracks_asset);
+}
+
+/// Saves the current track to `target`. When that is the open file, every track in it is saved.
+fn save_to_file(
+    data: &ControlPoints,
+    tracks_asset: &mut TracksAsset,
+    files: &mut EditorFiles,
+    target: &Path,
+) {
+    tracks_asset.update_current_track(data.points.clone(), data.widths.clone());
+    let result = if target == files.open {
+        files.library.save_file(target, tracks_asset)
+    } else {
+        let Some(track) = tracks_asset.get_current_track() else {
+            return;
+        };
+        files.library.save_track(target, track)
+    };
+    match result {
+        Ok(()) => info!("Saved to {}", target.display()),
+        Err(err) => error!("{err}"),
+    }
+}
+
+/// Replaces the tracks being edited with `loaded`, and starts editing its current track.
+fn open_tracks(loaded: TracksAsset, tracks_asset: &mut TracksAsset, control_points: &mut ControlPoints) {
+    *tracks_asset = loaded;
+    if tracks_asset.tracks.is_empty() {
+        tracks_asset.new_track();
+    }
+    if tracks_asset.get_current_track().is_none() {
+        tracks_asset.current_track_index = Some(0);
+    }
+    let race_track = tracks_asset.get_current_track().unwrap();
+    control_points.points = race_track.points.clone();
+    control_points.widths = race_track.point_widths();
+    control_points.selected = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dragging_in_mirror_mode_only_moves_the_dragged_point() {
+        let points = vec![
+            vec2(-300.0, -100.0),
+            vec2(120.0, -260.0),
+            vec2(340.0, 80.0),
+            vec2(-40.0, 310.0),
+        ];
+        let mut control_points = ControlPoints {
+            widths: vec![DEFAULT_TRACK_WIDTH; points.len()],
+            points: points.clone(),
+            selected: Some(1),
+        };
+        let grid = EditorGrid {
+            mirror: Some(MirrorAxis::Vertical),
+            ..default()
+        };
+        let drag = PointDrag {
+            dragging: Some((1, points[1])),
+            offset: Vec2::ZERO,
+        };
+
+        follow_cursor(&mut control_points, &drag, &grid, vec2(180.0, -200.0));
+
+        assert_eq!(control_points.points[1], vec2(180.0, -200.0));
+        for index in [0, 2, 3] {
+            assert_eq!(control_points.points[index], points[index], "point {index} moved");
         }
     }
 }
@@ -3,14 +3,18 @@
 
 use bevy::prelude::*;
 
-use crate::{asset_tracking::ResourceHandles, screens::Screen, theme::prelude::*};
+use crate::{
+    asset_tracking::ResourceHandles,
+    screens::{Screen, track_select::QuickRace},
+    theme::prelude::*,
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Loading), spawn_loading_screen);
 
     app.add_systems(
         Update,
-        enter_gameplay_screen.run_if(in_state(Screen::Loading).and(all_assets_loaded)),
+        enter_next_screen.run_if(in_state(Screen::Loading).and(all_assets_loaded)),
     );
 }
 
@@ -22,8 +26,16 @@ fn spawn_loading_screen(mut commands: Commands) {
     ));
 }
 
-fn enter_gameplay_screen(mut next_screen: ResMut<NextState<Screen>>) {
-    next_screen.set(Screen::Gameplay);
+/// Goes on to the quick race picked, or else to picking a cup.
+fn enter_next_screen(
+    quick_race: Option<Res<QuickRace>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if quick_race.is_some() {
+        next_screen.set(Screen::Gameplay);
+    } else {
+        next_screen.set(Screen::CupSelect);
+    }
 }
 
 fn all_assets_loaded(resource_handles: Res<ResourceHandles>) -> bool {
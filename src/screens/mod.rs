@@ -1,10 +1,13 @@
 //! The game's main screen states and transitions between them.
 
+pub mod cup_select;
 mod gameplay;
 mod loading;
 mod splash;
 mod title;
 mod editor;
+pub mod track_error;
+pub mod track_select;
 
 use bevy::prelude::*;
 
@@ -12,11 +15,14 @@ pub(super) fn plugin(app: &mut App) {
     app.init_state::<Screen>();
 
     app.add_plugins((
+        cup_select::plugin,
         gameplay::plugin,
         editor::plugin,
         loading::plugin,
         splash::plugin,
         title::plugin,
+        track_error::plugin,
+        track_select::plugin,
     ));
 }
 
@@ -30,4 +36,7 @@ pub enum Screen {
     Editor,
     Loading,
     Gameplay,
+    TrackError,
+    TrackSelect,
+    CupSelect,
 }
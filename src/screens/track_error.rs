@@ -0,0 +1,59 @@
+//! A screen explaining why the race tracks could not be loaded.
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::{screens::Screen, theme::prelude::*};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::TrackError), spawn_track_error_screen);
+    app.add_systems(OnExit(Screen::TrackError), clear_track_load_error);
+    app.add_systems(
+        Update,
+        go_back.run_if(in_state(Screen::TrackError).and(input_just_pressed(KeyCode::Escape))),
+    );
+}
+
+/// What went wrong the last time tracks were loaded. Insert this and switch to
+/// [`Screen::TrackError`] to show it to the player.
+#[derive(Resource, Debug, Clone)]
+pub struct TrackLoadError {
+    /// Where the tracks were loaded from.
+    pub source: String,
+    pub message: String,
+}
+
+fn spawn_track_error_screen(mut commands: Commands, error: Option<Res<TrackLoadError>>) {
+    let (source, message) = match error {
+        Some(error) => (error.source.clone(), error.message.clone()),
+        None => (String::new(), "Unknown error".to_string()),
+    };
+
+    commands.spawn((
+        widget::ui_root("Track Error Screen"),
+        StateScoped(Screen::TrackError),
+        children![
+            widget::header("Could not load tracks"),
+            widget::label(source),
+            (
+                widget::label(message),
+                Node {
+                    max_width: Val::Percent(80.0),
+                    ..default()
+                },
+            ),
+            widget::button("Back", go_back_on_click),
+        ],
+    ));
+}
+
+fn clear_track_load_error(mut commands: Commands) {
+    commands.remove_resource::<TrackLoadError>();
+}
+
+fn go_back_on_click(_: Trigger<Pointer<Click>>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
+
+fn go_back(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
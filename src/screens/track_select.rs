@@ -0,0 +1,92 @@
+//! A screen listing every track in the track library with its statistics, easiest first, to pick
+//! one for a quick race.
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*, ui::Val::*};
+
+use crate::{
+    asset_tracking::ResourceHandles,
+    racing::{RaceTrack, library::TrackLibrary},
+    screens::Screen,
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::TrackSelect), spawn_track_select_screen);
+    app.add_systems(
+        Update,
+        go_back.run_if(in_state(Screen::TrackSelect).and(input_just_pressed(KeyCode::Escape))),
+    );
+}
+
+/// A track picked on the track select screen. It is raced instead of the next race of the
+/// championship.
+#[derive(Resource, Debug, Clone)]
+pub struct QuickRace(pub RaceTrack);
+
+fn spawn_track_select_screen(mut commands: Commands) {
+    let library = TrackLibrary::scan();
+    for err in &library.errors {
+        warn!("{err}");
+    }
+    let mut entries = library
+        .catalogue()
+        .filter_map(|entry| Some((entry, entry.track.stats()?)))
+        .collect::<Vec<_>>();
+    entries.sort_by(|(_, a), (_, b)| a.difficulty.total_cmp(&b.difficulty));
+
+    let rows = entries
+        .into_iter()
+        .map(|(entry, stats)| {
+            let track = entry.track.clone();
+            (
+                Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Px(20.0),
+                    ..default()
+                },
+                children![
+                    widget::button(
+                        entry.track.track_name.clone(),
+                        move |_: Trigger<Pointer<Click>>,
+                              mut commands: Commands,
+                              resource_handles: Res<ResourceHandles>,
+                              mut next_screen: ResMut<NextState<Screen>>| {
+                            commands.insert_resource(QuickRace(track.clone()));
+                            if resource_handles.is_all_done() {
+                                next_screen.set(Screen::Gameplay);
+                            } else {
+                                next_screen.set(Screen::Loading);
+                            }
+                        },
+                    ),
+                    widget::label(format!("{} ({})", stats.summary(), entry.file.source.name())),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+
+    commands
+        .spawn((
+            widget::ui_root("Track Select Screen"),
+            StateScoped(Screen::TrackSelect),
+        ))
+        .with_children(|parent| {
+            parent.spawn(widget::header("Tracks"));
+            if rows.is_empty() {
+                parent.spawn(widget::label("No tracks found"));
+            }
+            for row in rows {
+                parent.spawn(row);
+            }
+            parent.spawn(widget::button("Back", go_back_on_click));
+        });
+}
+
+fn go_back_on_click(_: Trigger<Pointer<Click>>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
+
+fn go_back(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}